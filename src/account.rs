@@ -1,22 +1,33 @@
 use std::str::FromStr;
 
-use bip32::secp256k1::ecdsa::signature::Signer;
-use bip32::secp256k1::ecdsa::{Signature, VerifyingKey};
+use anyhow::Context;
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use bip32::secp256k1::ecdsa::signature::{SignatureEncoding, Signer};
+use bip32::secp256k1::ecdsa::{Signature, SigningKey, VerifyingKey};
 use bip32::{DerivationPath, PrivateKey, PublicKey};
 use cosmos_sdk_proto::cosmos::crypto::secp256k1::PubKey;
 use cosmos_sdk_proto::cosmos::tx::signing::v1beta1::SignMode;
 use cosmos_sdk_proto::cosmos::tx::v1beta1::{Tx, TxBody};
-use cosmos_sdk_proto::prost_wkt_types::Any;
+use cosmos_sdk_proto::prost_wkt_types::{Any, MessageSerde};
+use prost::Message;
 use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
 
+use crate::broadcast::{CheckTxResult, Endpoints};
 use crate::keys::{
-    from_pk_bytes_to_address, get_priv_key_from_memory, get_priv_key_from_os,
-    get_uncompressed_pub_key_from_memory, get_uncompressed_pub_key_from_os, AddressType,
+    cosmos_key_derive, ethereum_key_derive, from_pk_bytes_to_address, get_priv_key_from_file,
+    get_priv_key_from_memory, get_priv_key_from_os, get_uncompressed_pub_key_from_file,
+    get_uncompressed_pub_key_from_memory, get_uncompressed_pub_key_from_os, save_key_to_file,
+    save_key_to_memory, save_key_to_os, AddressType,
 };
 use crate::ledger::{get_pub_key, get_signature};
+use crate::multisig::{multisig_address, multisig_public_key, sort_public_keys, PartialSignature};
+use crate::proto::{ETH_SECP256K1_PUBKEY_TYPE_URL, INJECTIVE_PUBKEY_TYPE_URL};
+use crate::sequence::{expected_sequence, SequenceTracker, SDK_CODESPACE, SEQUENCE_MISMATCH_CODE};
 use crate::txs::{
     create_transaction, generate_auth_info, generate_legacy_amino_json,
-    get_account_number_and_sequence,
+    get_account_number_and_sequence, get_sequence, has_public_key, set_public_key, set_sequence,
+    set_sign_mode, TxOptions,
 };
 use crate::Result;
 
@@ -25,55 +36,100 @@ pub enum KeyStoreBackend {
     Ledger,
     Os(String),
     Memory(String),
+    File(String),
+    /// threshold multisig, with base64 encoded secp256k1 member keys
+    Multisig {
+        threshold: u32,
+        public_keys: Vec<String>,
+    },
 }
 
 impl KeyStoreBackend {
-    pub async fn get_signature(&self, data: &[u8]) -> Result<[u8; 64]> {
+    pub fn save_private_key(&self, bytes: &[u8]) -> Result<()> {
         match &self {
+            Self::Ledger => Err(anyhow::anyhow!(
+                "private keys can not be imported to ledger"
+            )),
+            Self::Os(key) => save_key_to_os(bytes, key),
+            Self::Memory(key) => save_key_to_memory(bytes, key),
+            Self::File(key) => save_key_to_file(bytes, key),
+            Self::Multisig { .. } => Err(anyhow::anyhow!("multisig accounts have no private key")),
+        }
+    }
+
+    pub fn private_key(&self) -> Result<SigningKey> {
+        match &self {
+            Self::Ledger => Err(anyhow::anyhow!("ledger never exposes its private keys")),
+            Self::Os(key) => get_priv_key_from_os(key),
+            Self::Memory(key) => get_priv_key_from_memory(key),
+            Self::File(key) => get_priv_key_from_file(key),
+            Self::Multisig { .. } => Err(anyhow::anyhow!("multisig accounts have no private key")),
+        }
+    }
+
+    pub async fn get_signature(
+        &self,
+        data: &[u8],
+        hd_path: &DerivationPath,
+        addr_type: &AddressType,
+    ) -> Result<Vec<u8>> {
+        let priv_key = match &self {
             Self::Ledger => {
+                (addr_type == &AddressType::Cosmos)
+                    .then_some(())
+                    .context("ledger supports only cosmos secp256k1 keys")?;
                 let value: serde_json::Value = serde_json::from_slice(data)?;
-                Ok(get_signature(value, &DerivationPath::from_str("m/44'/118'/0'/0/0")?).await?)
+                return Ok(get_signature(value, hd_path).await?.to_vec());
             }
-            Self::Os(key) => {
-                let priv_key = get_priv_key_from_os(key)?;
-                let signature: Signature = priv_key.try_sign(data).unwrap();
-                Ok(signature.to_bytes().into())
+            _ => self.private_key()?,
+        };
+        match addr_type {
+            AddressType::Cosmos => {
+                let signature: Signature = priv_key
+                    .try_sign(data)
+                    .map_err(|e| anyhow::anyhow!("signing failed: {e}"))?;
+                Ok(signature.to_vec())
             }
-            Self::Memory(key) => {
-                let priv_key = get_priv_key_from_memory(key)?;
-                let signature: Signature = priv_key.try_sign(data).unwrap();
-                Ok(signature.to_bytes().into())
+            AddressType::Ethereum | AddressType::Injective => {
+                // ethermint signs the keccak256 digest, with the recovery id appended
+                let (signature, recovery_id) = priv_key
+                    .sign_prehash_recoverable(&Keccak256::digest(data))
+                    .map_err(|e| anyhow::anyhow!("signing failed: {e}"))?;
+                let mut bytes = signature.to_vec();
+                bytes.push(recovery_id.to_byte());
+                Ok(bytes)
             }
         }
     }
 
-    pub async fn public_key(&self) -> Result<VerifyingKey> {
+    pub async fn public_key(&self, hd_path: &DerivationPath) -> Result<VerifyingKey> {
         Ok(match &self {
-            Self::Ledger => VerifyingKey::from_sec1_bytes(
-                &get_pub_key(
-                    "cosmos",
-                    &DerivationPath::from_str("m/44'/118'/0'/0/0")?,
-                    false,
-                )
-                .await?[..33],
-            )
-            .expect("bip32 error"),
+            Self::Ledger => {
+                let pub_key = get_pub_key("cosmos", hd_path, false).await?;
+                VerifyingKey::from_sec1_bytes(&pub_key[..33]).expect("bip32 error")
+            }
             Self::Os(key) => get_priv_key_from_os(key)?.public_key(),
             Self::Memory(key) => get_priv_key_from_memory(key)?.public_key(),
+            Self::File(key) => get_priv_key_from_file(key)?.public_key(),
+            Self::Multisig { .. } => {
+                return Err(anyhow::anyhow!(
+                    "multisig accounts have no single public key"
+                ))
+            }
         })
     }
 
-    pub async fn uncompressed_public_key_bytes(&self) -> Result<Vec<u8>> {
+    pub async fn uncompressed_public_key_bytes(&self, hd_path: &DerivationPath) -> Result<Vec<u8>> {
         Ok(match &self {
-            Self::Ledger => get_pub_key(
-                "cosmos",
-                &DerivationPath::from_str("m/44'/118'/0'/0/0")?,
-                false,
-            )
-            .await?[1..]
-                .to_vec(),
+            Self::Ledger => get_pub_key("cosmos", hd_path, false).await?[1..].to_vec(),
             Self::Os(key) => get_uncompressed_pub_key_from_os(key)?,
             Self::Memory(key) => get_uncompressed_pub_key_from_memory(key)?,
+            Self::File(key) => get_uncompressed_pub_key_from_file(key)?,
+            Self::Multisig { .. } => {
+                return Err(anyhow::anyhow!(
+                    "multisig accounts have no single public key"
+                ))
+            }
         })
     }
 }
@@ -82,28 +138,159 @@ impl KeyStoreBackend {
 pub struct Account {
     cosmos_address: String,
     private_key_backend: KeyStoreBackend,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hd_path: Option<String>,
+    /// missing for the accounts added before it was stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    addr_type: Option<AddressType>,
 }
 
 impl Account {
     pub async fn new(
         private_key_backend: KeyStoreBackend,
         addr_type: &AddressType,
+        hd_path: Option<String>,
     ) -> Result<Self> {
+        let derivation_path = DerivationPath::from_str(
+            &hd_path
+                .clone()
+                .unwrap_or_else(|| addr_type.default_hd_path()),
+        )?;
         let public_key = match addr_type {
-            AddressType::Cosmos => private_key_backend.public_key().await?.to_bytes().to_vec(),
-            AddressType::Ethereum => private_key_backend.uncompressed_public_key_bytes().await?,
+            AddressType::Cosmos => private_key_backend
+                .public_key(&derivation_path)
+                .await?
+                .to_bytes()
+                .to_vec(),
+            AddressType::Ethereum | AddressType::Injective => {
+                private_key_backend
+                    .uncompressed_public_key_bytes(&derivation_path)
+                    .await?
+            }
         };
         let address = from_pk_bytes_to_address(&public_key, addr_type)?;
+        // the other backends store an already derived key
+        let hd_path = hd_path.filter(|_| matches!(private_key_backend, KeyStoreBackend::Ledger));
         Ok(Self {
             cosmos_address: address,
             private_key_backend,
+            hd_path,
+            addr_type: Some(addr_type.clone()),
         })
     }
 
+    pub fn new_multisig(threshold: u32, mut public_keys: Vec<Vec<u8>>) -> Result<Self> {
+        (threshold > 0 && threshold as usize <= public_keys.len())
+            .then_some(())
+            .context("threshold must be between 1 and the number of members")?;
+        sort_public_keys(&mut public_keys);
+        Ok(Self {
+            // stored under the cosmos prefix, like the other accounts
+            cosmos_address: multisig_address(threshold, &public_keys, "cosmos")?,
+            private_key_backend: KeyStoreBackend::Multisig {
+                threshold,
+                public_keys: public_keys
+                    .iter()
+                    .map(|key| BASE64_STANDARD.encode(key))
+                    .collect(),
+            },
+            hd_path: None,
+            addr_type: Some(AddressType::Cosmos),
+        })
+    }
+
+    /// Threshold and member keys, if this is a multisig account.
+    pub fn multisig(&self) -> Result<Option<(u32, Vec<Vec<u8>>)>> {
+        match &self.private_key_backend {
+            KeyStoreBackend::Multisig {
+                threshold,
+                public_keys,
+            } => Ok(Some((
+                *threshold,
+                public_keys
+                    .iter()
+                    .map(|key| Ok(BASE64_STANDARD.decode(key)?))
+                    .collect::<Result<_>>()?,
+            ))),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn is_multisig(&self) -> bool {
+        matches!(self.private_key_backend, KeyStoreBackend::Multisig { .. })
+    }
+
+    pub fn hd_path(&self) -> Result<DerivationPath> {
+        // only Ledger accounts derive their keys, and those are cosmos accounts
+        Ok(DerivationPath::from_str(
+            &self
+                .hd_path
+                .clone()
+                .unwrap_or_else(|| self.addr_type.clone().unwrap_or_default().default_hd_path()),
+        )?)
+    }
+
+    /// Address type of the account. For the accounts added before it was
+    /// stored, it is the one whose derivation of the key gives the address.
+    pub async fn addr_type(&self) -> Result<AddressType> {
+        if let Some(addr_type) = &self.addr_type {
+            return Ok(addr_type.clone());
+        }
+
+        let (_, address) = bech32::decode(&self.cosmos_address)?;
+
+        if cosmos_key_derive(&self.public_key_bytes().await?) == address {
+            return Ok(AddressType::Cosmos);
+        }
+
+        let uncompressed_public_key = self
+            .private_key_backend
+            .uncompressed_public_key_bytes(&self.hd_path()?)
+            .await?;
+        (ethereum_key_derive(&uncompressed_public_key) == address)
+            .then_some(AddressType::Ethereum)
+            .context(format!(
+                "{} is derived from neither a cosmos nor an ethereum key, add the account again",
+                self.cosmos_address
+            ))
+    }
+
     pub fn address(&self, prefix: &str) -> Result<String> {
         crate::utils::bech32(&self.cosmos_address, prefix)
     }
 
+    pub async fn public_key_bytes(&self) -> Result<Vec<u8>> {
+        Ok(self
+            .private_key_backend
+            .public_key(&self.hd_path()?)
+            .await?
+            .to_bytes()
+            .to_vec())
+    }
+
+    pub async fn public_key_any(&self) -> Result<Any> {
+        if let Some((threshold, public_keys)) = self.multisig()? {
+            return Ok(Any::try_pack(multisig_public_key(
+                threshold,
+                &public_keys,
+            )?)?);
+        }
+        let public_key = PubKey {
+            key: self.public_key_bytes().await?,
+        };
+        Ok(match self.addr_type().await? {
+            AddressType::Cosmos => Any::try_pack(public_key)?,
+            AddressType::Ethereum => Any {
+                type_url: ETH_SECP256K1_PUBKEY_TYPE_URL.into(),
+                value: public_key.encode_to_vec(),
+            },
+            AddressType::Injective => Any {
+                type_url: INJECTIVE_PUBKEY_TYPE_URL.into(),
+                value: public_key.encode_to_vec(),
+            },
+        })
+    }
+
     pub async fn generate_unsigned_transaction(
         &self,
         address: &str,
@@ -111,76 +298,150 @@ impl Account {
         fee: (u128, &str),
         fee_granter: &str,
         rpc_endpoint: &str,
+        options: &TxOptions,
     ) -> Result<(u64, Tx)> {
         let (fee_amount, fee_denom) = fee;
         let tx_body = TxBody {
             messages: any_msgs.to_vec(),
-            memo: "".into(),
+            memo: options.memo.clone(),
+            timeout_height: options.timeout_height,
             ..Default::default()
         };
 
-        let (account_number, sequence, mut public_key_on_chain) =
+        let (account_number, sequence, public_key_on_chain) =
             get_account_number_and_sequence(rpc_endpoint, address).await?;
 
-        if public_key_on_chain.is_none() {
-            public_key_on_chain = Some(PubKey {
-                key: self
-                    .private_key_backend
-                    .public_key()
-                    .await?
-                    .to_bytes()
-                    .to_vec(),
-            });
-        }
-
-        let public_key = public_key_on_chain.expect("never");
+        // without the private key backend at hand, `sign` adds the public key
+        let public_key = match public_key_on_chain {
+            Some(public_key) => Some(public_key),
+            None => self.public_key_any().await.ok(),
+        };
 
         let auth_info = generate_auth_info(
             public_key,
             sequence,
-            400_000,
+            options.gas_limit,
             &fee_amount,
             fee_denom,
             fee_granter,
-            match self.private_key_backend {
-                KeyStoreBackend::Ledger => SignMode::LegacyAminoJson,
-                _ => SignMode::Direct,
-            },
+            self.sign_mode(),
         )?;
 
         Ok((account_number, create_transaction(tx_body, auth_info)))
     }
 
+    /// Ledger signs legacy amino JSON only, and so do multisig members.
+    pub fn sign_mode(&self) -> SignMode {
+        match self.private_key_backend {
+            KeyStoreBackend::Ledger | KeyStoreBackend::Multisig { .. } => SignMode::LegacyAminoJson,
+            _ => SignMode::Direct,
+        }
+    }
+
     pub async fn sign_unsigned_transaction(
         &self,
         unsigned_tx: &Tx,
         chain_id: &str,
         account_number: u64,
     ) -> Result<Tx> {
-        Ok(match &self.private_key_backend {
-            KeyStoreBackend::Ledger => {
-                let payload = generate_legacy_amino_json(unsigned_tx, chain_id, account_number)?;
-                let signature = crate::ledger::get_signature(
-                    payload,
-                    &DerivationPath::from_str("m/44'/118'/0'/0/0")?,
+        // the transaction may be generated on another machine, with another backend
+        let mut unsigned_tx = set_sign_mode(unsigned_tx.clone(), self.sign_mode())?;
+        if !has_public_key(&unsigned_tx)? {
+            unsigned_tx = set_public_key(unsigned_tx, self.public_key_any().await?)?;
+        }
+        let unsigned_tx = &unsigned_tx;
+
+        let sign_bytes = match &self.private_key_backend {
+            KeyStoreBackend::Ledger => serde_json::to_vec(&generate_legacy_amino_json(
+                unsigned_tx,
+                chain_id,
+                account_number,
+            )?)?,
+            _ => crate::txs::generate_sign_doc(unsigned_tx, chain_id, account_number)?
+                .try_encoded()?,
+        };
+        let signature = self
+            .private_key_backend
+            .get_signature(&sign_bytes, &self.hd_path()?, &self.addr_type().await?)
+            .await?;
+        Ok(crate::txs::update_signature(
+            unsigned_tx.clone(),
+            &signature,
+        ))
+    }
+
+    /// Signs with the next sequence of `tracker` and broadcasts. On an account
+    /// sequence mismatch, re-signs with the expected sequence and retries.
+    pub async fn sign_and_broadcast(
+        &self,
+        unsigned_tx: &Tx,
+        chain_id: &str,
+        account_number: u64,
+        address: &str,
+        endpoints: &Endpoints,
+        tracker: &SequenceTracker,
+    ) -> Result<(Tx, CheckTxResult)> {
+        const MAX_RETRIES: usize = 3;
+
+        let mut sequence = tracker.next(chain_id, address, get_sequence(unsigned_tx)?);
+
+        for _ in 0..=MAX_RETRIES {
+            let signed_tx = self
+                .sign_unsigned_transaction(
+                    &set_sequence(unsigned_tx.clone(), sequence)?,
+                    chain_id,
+                    account_number,
                 )
                 .await?;
-                crate::txs::update_signature(unsigned_tx.clone(), &signature)
-            }
-            KeyStoreBackend::Os(key) => {
-                let priv_key = get_priv_key_from_os(key)?;
-                let sign_doc =
-                    crate::txs::generate_sign_doc(unsigned_tx, chain_id, account_number)?;
-                let signature = crate::txs::generate_signature_from_sign_doc(sign_doc, &priv_key)?;
-                crate::txs::update_signature(unsigned_tx.clone(), &signature)
+
+            let resp = endpoints.broadcast(&signed_tx).await?;
+
+            if resp.is_ok() {
+                tracker.accepted(chain_id, address, sequence);
+                return Ok((signed_tx, resp));
             }
-            KeyStoreBackend::Memory(key) => {
-                let priv_key = get_priv_key_from_memory(key)?;
-                let sign_doc =
-                    crate::txs::generate_sign_doc(unsigned_tx, chain_id, account_number)?;
-                let signature = crate::txs::generate_signature_from_sign_doc(sign_doc, &priv_key)?;
-                crate::txs::update_signature(unsigned_tx.clone(), &signature)
+
+            match expected_sequence(&resp.raw_log) {
+                Some(expected)
+                    if resp.codespace == SDK_CODESPACE
+                        && resp.code == SEQUENCE_MISMATCH_CODE
+                        && expected != sequence =>
+                {
+                    println!("sequence {sequence} is stale, retrying with {expected}");
+                    tracker.set(chain_id, address, expected);
+                    sequence = expected;
+                }
+                _ => {
+                    // the earlier accepted transactions may be dropped too, so the
+                    // next transaction starts again from the on-chain sequence
+                    tracker.reset(chain_id, address);
+                    return Ok((signed_tx, resp));
+                }
             }
+        }
+
+        anyhow::bail!("account sequence mismatch after {MAX_RETRIES} retries")
+    }
+
+    /// Signs as a member of a multisig account, which signs in legacy amino JSON mode.
+    pub async fn sign_legacy_amino_json(
+        &self,
+        unsigned_tx: &Tx,
+        chain_id: &str,
+        account_number: u64,
+    ) -> Result<PartialSignature> {
+        let sign_bytes = serde_json::to_vec(&generate_legacy_amino_json(
+            unsigned_tx,
+            chain_id,
+            account_number,
+        )?)?;
+        let signature = self
+            .private_key_backend
+            .get_signature(&sign_bytes, &self.hd_path()?, &self.addr_type().await?)
+            .await?;
+        Ok(PartialSignature {
+            public_key: BASE64_STANDARD.encode(self.public_key_bytes().await?),
+            signature: BASE64_STANDARD.encode(signature),
         })
     }
 }
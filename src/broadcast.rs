@@ -1,14 +1,25 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
 use anyhow::Context;
+use base64::prelude::{Engine as _, BASE64_STANDARD};
 use cosmos_sdk_proto::cosmos::base::abci::v1beta1::TxResponse;
 use cosmos_sdk_proto::cosmos::tx::v1beta1::service_client::ServiceClient;
 use cosmos_sdk_proto::cosmos::tx::v1beta1::{
-    BroadcastMode, BroadcastTxRequest, BroadcastTxResponse, SimulateRequest, SimulateResponse, Tx,
+    BroadcastMode, BroadcastTxRequest, BroadcastTxResponse, GetTxRequest, SimulateRequest,
+    SimulateResponse, Tx,
 };
 use cosmos_sdk_proto::prost_wkt_types::MessageSerde;
+use futures::StreamExt;
 use serde::Serialize;
+use serde_json::Value;
+use tendermint::Hash;
 use tendermint_rpc::endpoint::broadcast::tx_sync::Response as TendermintResponse;
+use tendermint_rpc::endpoint::tx::Response as TendermintTxResponse;
 use tendermint_rpc::{Client, HttpClient};
 
+use crate::data::Broadcast;
 use crate::Result;
 
 pub fn create_broadcast_sync_payload(tx: &Tx) -> Result<BroadcastTxRequest> {
@@ -18,12 +29,15 @@ pub fn create_broadcast_sync_payload(tx: &Tx) -> Result<BroadcastTxRequest> {
     })
 }
 
-pub async fn broadcast_via_rest(endpoint: &str, signed_tx: &Tx) -> Result<TxResponse> {
-    let broadcast_req = create_broadcast_sync_payload(signed_tx)?;
+pub async fn broadcast_via_rest(endpoint: &str, signed_tx: &Tx) -> Result<CheckTxResult> {
     let url = format!("{endpoint}/cosmos/tx/v1beta1/txs");
-    Ok(serde_json::from_value(
-        ureq::post(&url).send_json(broadcast_req)?.into_json()?,
-    )?)
+    let resp: Value = ureq::post(&url)
+        .send_json(serde_json::json!({
+            "tx_bytes": BASE64_STANDARD.encode(signed_tx.try_encoded()?),
+            "mode": "BROADCAST_MODE_SYNC",
+        }))?
+        .into_json()?;
+    CheckTxResult::from_rest_json(resp.get("tx_response").context("no tx_response")?)
 }
 
 pub async fn broadcast_via_tendermint_rpc(
@@ -82,3 +96,343 @@ where
         .parse()
         .context("parse failed")
 }
+
+/// Final `DeliverTx` result of an included transaction.
+#[derive(Serialize, Debug, Clone)]
+pub struct TxResult {
+    pub hash: String,
+    pub height: u64,
+    pub code: u32,
+    pub codespace: String,
+    pub gas_wanted: i64,
+    pub gas_used: i64,
+    pub raw_log: String,
+    pub events: serde_json::Value,
+}
+
+impl TxResult {
+    pub fn is_ok(&self) -> bool {
+        self.code == 0
+    }
+
+    /// Attributes of the events of a type. Tendermint v0.34 nodes encode the
+    /// attributes in base64, so they are kept both as is and decoded.
+    pub fn events_of(&self, kind: &str) -> Vec<HashMap<String, String>> {
+        let decode = |text: &str| {
+            BASE64_STANDARD
+                .decode(text)
+                .ok()
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+        };
+
+        self.events
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|event| {
+                event
+                    .get("type")
+                    .or_else(|| event.get("kind"))
+                    .and_then(Value::as_str)
+                    == Some(kind)
+            })
+            .map(|event| {
+                event
+                    .get("attributes")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|attribute| {
+                        let key = attribute.get("key")?.as_str()?;
+                        let value = attribute
+                            .get("value")
+                            .and_then(Value::as_str)
+                            .unwrap_or_default();
+                        Some((key, value))
+                    })
+                    .flat_map(|(key, value)| {
+                        let decoded = decode(key).zip(decode(value));
+                        std::iter::once((key.to_string(), value.to_string())).chain(decoded)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl TryFrom<TendermintTxResponse> for TxResult {
+    type Error = anyhow::Error;
+
+    fn try_from(resp: TendermintTxResponse) -> Result<Self> {
+        Ok(Self {
+            hash: resp.hash.to_string(),
+            height: resp.height.value(),
+            code: resp.tx_result.code.value(),
+            codespace: resp.tx_result.codespace,
+            gas_wanted: resp.tx_result.gas_wanted,
+            gas_used: resp.tx_result.gas_used,
+            raw_log: resp.tx_result.log,
+            events: serde_json::to_value(resp.tx_result.events)?,
+        })
+    }
+}
+
+impl TryFrom<TxResponse> for TxResult {
+    type Error = anyhow::Error;
+
+    fn try_from(resp: TxResponse) -> Result<Self> {
+        Ok(Self {
+            hash: resp.txhash,
+            height: resp.height.try_into()?,
+            code: resp.code,
+            codespace: resp.codespace,
+            gas_wanted: resp.gas_wanted,
+            gas_used: resp.gas_used,
+            raw_log: resp.raw_log,
+            events: serde_json::to_value(resp.events)?,
+        })
+    }
+}
+
+impl TxResult {
+    fn from_rest_json(resp: &Value) -> Result<Self> {
+        let field = |name: &str| resp.get(name).context(format!("no {name}"));
+        let str_field = |name: &str| -> Result<String> {
+            Ok(field(name)?.as_str().context("not str")?.to_owned())
+        };
+        Ok(Self {
+            hash: str_field("txhash")?,
+            height: str_field("height")?.parse()?,
+            code: field("code")?.as_u64().context("not u64")?.try_into()?,
+            codespace: str_field("codespace")?,
+            gas_wanted: str_field("gas_wanted")?.parse()?,
+            gas_used: str_field("gas_used")?.parse()?,
+            raw_log: str_field("raw_log")?,
+            events: field("events")?.clone(),
+        })
+    }
+}
+
+/// `CheckTx` result of a broadcast transaction.
+#[derive(Debug, Clone)]
+pub struct CheckTxResult {
+    pub hash: String,
+    pub code: u32,
+    pub codespace: String,
+    pub raw_log: String,
+}
+
+impl CheckTxResult {
+    pub fn is_ok(&self) -> bool {
+        self.code == 0
+    }
+
+    fn from_rest_json(resp: &Value) -> Result<Self> {
+        Ok(Self {
+            hash: resp
+                .get("txhash")
+                .and_then(Value::as_str)
+                .context("no txhash")?
+                .into(),
+            code: resp
+                .get("code")
+                .and_then(Value::as_u64)
+                .context("no code")?
+                .try_into()?,
+            codespace: resp
+                .get("codespace")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .into(),
+            raw_log: resp
+                .get("raw_log")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .into(),
+        })
+    }
+}
+
+impl From<TendermintResponse> for CheckTxResult {
+    fn from(resp: TendermintResponse) -> Self {
+        Self {
+            hash: resp.hash.to_string(),
+            code: resp.code.value(),
+            codespace: resp.codespace,
+            raw_log: resp.log,
+        }
+    }
+}
+
+impl From<TxResponse> for CheckTxResult {
+    fn from(resp: TxResponse) -> Self {
+        Self {
+            hash: resp.txhash,
+            code: resp.code,
+            codespace: resp.codespace,
+            raw_log: resp.raw_log,
+        }
+    }
+}
+
+pub async fn simulate_via_rest(endpoint: &str, tx: &Tx) -> Result<u64> {
+    let url = format!("{endpoint}/cosmos/tx/v1beta1/simulate");
+    let resp: Value = ureq::post(&url)
+        .send_json(serde_json::json!({
+            "tx_bytes": BASE64_STANDARD.encode(tx.try_encoded()?),
+        }))?
+        .into_json()?;
+    resp.pointer("/gas_info/gas_used")
+        .context("invalid pointer")?
+        .as_str()
+        .context("not str")?
+        .parse()
+        .context("parse failed")
+}
+
+pub async fn get_tx_via_tendermint_rpc(endpoint: &str, hash: &str) -> Result<TxResult> {
+    let rpc_client = HttpClient::new(endpoint).context("invalid endpoint")?;
+    rpc_client
+        .tx(Hash::from_str(hash)?, false)
+        .await?
+        .try_into()
+}
+
+pub async fn get_tx_via_rest(endpoint: &str, hash: &str) -> Result<TxResult> {
+    let url = format!("{endpoint}/cosmos/tx/v1beta1/txs/{hash}");
+    let resp: Value = ureq::get(&url).call()?.into_json()?;
+    TxResult::from_rest_json(resp.get("tx_response").context("no tx_response")?)
+}
+
+pub async fn get_tx_via_grpc(endpoint: &str, hash: &str) -> Result<TxResult> {
+    let mut service_client = ServiceClient::connect(endpoint.to_owned()).await?;
+    service_client
+        .get_tx(GetTxRequest { hash: hash.into() })
+        .await?
+        .into_inner()
+        .tx_response
+        .context("no tx_response")?
+        .try_into()
+}
+
+/// Endpoints of a chain for each transport. Simulation, broadcast and
+/// confirmation start with the preferred transport, and fall back to the others.
+#[derive(Debug, Clone, Default)]
+pub struct Endpoints {
+    pub preferred: Broadcast,
+    pub rpc: Vec<String>,
+    pub rest: Vec<String>,
+    pub grpc: Vec<String>,
+}
+
+impl Endpoints {
+    fn ordered(&self) -> Vec<(Broadcast, &str)> {
+        let mut transports = vec![Broadcast::Rpc, Broadcast::Rest, Broadcast::Grpc];
+        transports.sort_by_key(|transport| *transport != self.preferred);
+        transports
+            .into_iter()
+            .flat_map(|transport| {
+                match transport {
+                    Broadcast::Rpc => &self.rpc,
+                    Broadcast::Rest => &self.rest,
+                    Broadcast::Grpc => &self.grpc,
+                }
+                .iter()
+                .map(move |endpoint| (transport, endpoint.as_str()))
+            })
+            .collect()
+    }
+
+    pub async fn simulate(&self, tx: &Tx) -> Result<u64> {
+        futures::stream::iter(self.ordered())
+            .then(|(transport, endpoint)| async move {
+                println!("simulating with {transport:?} {endpoint}");
+                let simulation = async {
+                    match transport {
+                        Broadcast::Rpc => simulate_via_tendermint_rpc(endpoint, tx.clone()).await,
+                        Broadcast::Rest => simulate_via_rest(endpoint, tx).await,
+                        Broadcast::Grpc => Ok(simulate_via_grpc(endpoint, tx.clone())
+                            .await?
+                            .gas_info
+                            .context("no gas_info")?
+                            .gas_used),
+                    }
+                };
+                tokio::time::timeout(Duration::from_secs(5), simulation).await?
+            })
+            .filter_map(|x| async { x.ok() })
+            .boxed_local()
+            .next()
+            .await
+            .context("not able to simulate tx")
+    }
+
+    /// Broadcasts to the first reachable endpoint, and returns its `CheckTx`
+    /// result. A rejection is the same on every node, so only unreachable
+    /// endpoints fall through to the next one.
+    pub async fn broadcast(&self, signed_tx: &Tx) -> Result<CheckTxResult> {
+        futures::stream::iter(self.ordered())
+            .then(|(transport, endpoint)| async move {
+                println!("broadcasting trying with {transport:?} {endpoint}");
+                let broadcast = async {
+                    Result::Ok(match transport {
+                        Broadcast::Rpc => broadcast_via_tendermint_rpc(endpoint, signed_tx)
+                            .await?
+                            .into(),
+                        Broadcast::Rest => broadcast_via_rest(endpoint, signed_tx).await?,
+                        Broadcast::Grpc => broadcast_via_grpc(endpoint, signed_tx.clone())
+                            .await?
+                            .tx_response
+                            .context("no tx_response")?
+                            .into(),
+                    })
+                };
+                let resp = tokio::time::timeout(Duration::from_secs(5), broadcast).await??;
+
+                println!("{resp:?}");
+
+                Result::Ok(resp)
+            })
+            .filter_map(|x| async { x.ok() })
+            .boxed_local()
+            .next()
+            .await
+            .context("not able to broadcast tx")
+    }
+
+    /// Polls until the transaction is included in a block, or the timeout is over.
+    pub async fn wait_for_tx(&self, hash: &str, timeout: Duration) -> Result<TxResult> {
+        let start = Instant::now();
+
+        loop {
+            let tx_result = futures::stream::iter(self.ordered())
+                .then(|(transport, endpoint)| async move {
+                    let query = async {
+                        match transport {
+                            Broadcast::Rpc => get_tx_via_tendermint_rpc(endpoint, hash).await,
+                            Broadcast::Rest => get_tx_via_rest(endpoint, hash).await,
+                            Broadcast::Grpc => get_tx_via_grpc(endpoint, hash).await,
+                        }
+                    };
+                    tokio::time::timeout(Duration::from_secs(5), query).await?
+                })
+                .filter_map(|x| async { x.ok() })
+                .boxed_local()
+                .next()
+                .await;
+
+            match tx_result {
+                Some(tx_result) => return Ok(tx_result),
+                None if start.elapsed() < timeout => {
+                    tokio::time::sleep(Duration::from_secs(1)).await
+                }
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "{hash} is not included in {}s",
+                        timeout.as_secs()
+                    ))
+                }
+            }
+        }
+    }
+}
@@ -1,9 +1,71 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+use crate::data::Broadcast;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct Chain {
     pub chain_id: String,
     pub prefix: String,
     pub fee: u128,
     pub denom: String,
+    /// preferred transport for simulation and broadcast
+    #[serde(default)]
+    pub broadcast: Broadcast,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rest_endpoints: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub grpc_endpoints: Vec<String>,
+    /// used instead of the flat `fee`, when present for the fee denom
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gas_prices: Vec<GasPrices>,
+    /// whether the chain has a dynamic base fee, to query before each transaction
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub fee_market: bool,
+    /// cap on the fee amount, as dynamic base fees may spike
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_fee: Option<u128>,
+    /// bech32 prefix of operator addresses, `<prefix>valoper` if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valoper_prefix: Option<String>,
+}
+
+impl Chain {
+    pub fn valoper_prefix(&self) -> String {
+        self.valoper_prefix
+            .clone()
+            .unwrap_or_else(|| format!("{}valoper", self.prefix))
+    }
+
+    pub fn gas_price(&self, denom: &str, tier: FeeTier) -> Option<f64> {
+        self.gas_prices
+            .iter()
+            .find(|gas_prices| gas_prices.denom == denom)
+            .map(|gas_prices| gas_prices.price(tier))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GasPrices {
+    pub denom: String,
+    pub low: f64,
+    pub average: f64,
+    pub high: f64,
+}
+
+impl GasPrices {
+    pub fn price(&self, tier: FeeTier) -> f64 {
+        match tier {
+            FeeTier::Low => self.low,
+            FeeTier::Average => self.average,
+            FeeTier::High => self.high,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, clap::ValueEnum)]
+pub enum FeeTier {
+    Low,
+    #[default]
+    Average,
+    High,
 }
@@ -1,20 +1,35 @@
 use std::collections::HashMap;
 
 use anyhow::Context;
+use base64::prelude::{Engine as _, BASE64_STANDARD};
 use clap::Parser;
-use cosmos_sdk_proto::cosmos::base::v1beta1::Coin;
+use cosmos_sdk_proto::cosmos::tx::v1beta1::Tx;
+use futures::StreamExt;
 
 use crate::account::{Account, KeyStoreBackend};
 use crate::endpoint::{get_rpc_endpoints, transform_to_grpc_endpoint};
-use crate::keys::{save_key_to_os_from_mmseed, AddressType};
-use crate::query::{get_chain_id_info, get_chain_id_rpc, get_rpc_endpoint_chain_info};
-use crate::utils::{read_data_from_yaml, write_data_as_yaml};
+use crate::keys::{save_key_to_file_from_mmseed, save_key_to_os_from_mmseed, AddressType};
+use crate::multisig::{combine_signatures, PartialSignature};
+use crate::query::{
+    find_transfer_channel, get_chain_id_info, get_chain_id_rpc, get_rpc_endpoint_chain_info,
+};
+use crate::txs::{
+    generate_legacy_amino_json, inspect_transaction, signer_public_key, verify_transaction, TxFile,
+};
+use crate::utils::{
+    read_data_from_json, read_data_from_yaml, read_data_from_yaml_or_default, read_from_base64,
+    read_from_bytes, write_data_as_json, write_data_as_yaml,
+};
 use crate::Result;
 
 pub mod tx;
 pub mod utils;
 
-use utils::{custom_coin, custom_keystorebackend};
+use tx::{broadcast_and_wait, collect_endpoints};
+use utils::{
+    bip39_passphrase, custom_io_bytes, custom_io_string, custom_keystorebackend, write_tx_file,
+    BroadcastArgs, HdPathArgs, TxArgs, TxEncoding, TxFileArgs, TxOutputArgs,
+};
 
 #[derive(Parser, Debug)]
 pub enum Args {
@@ -27,23 +42,104 @@ pub enum Args {
         chain_id: String,
     },
     Tx {
-        #[clap(short = 'n', long)]
-        dry_run: bool,
+        #[clap(flatten)]
+        broadcast: BroadcastArgs,
         chain_id: String,
         executor: Option<String>,
         #[clap(long, short)]
         rpc: Option<String>,
-        #[clap(long, short, value_parser(custom_coin))]
-        fee: Option<Coin>,
+        #[clap(flatten)]
+        options: TxArgs,
+        #[clap(flatten)]
+        output: TxOutputArgs,
         #[clap(subcommand)]
         transaction: tx::Transaction,
     },
+    /// Signs a transaction generated with `tx --generate-only`, without network access
+    Sign {
+        file: String,
+        signer: String,
+        #[clap(flatten)]
+        tx_file: TxFileArgs,
+        #[clap(long, short)]
+        output: Option<String>,
+        #[clap(long, value_enum, default_value_t)]
+        encoding: TxEncoding,
+    },
+    /// Broadcasts a signed transaction file
+    Broadcast {
+        file: String,
+        #[clap(long)]
+        chain_id: Option<String>,
+        #[clap(long, short)]
+        rpc: Option<String>,
+        #[clap(flatten)]
+        broadcast: BroadcastArgs,
+    },
+    /// Decodes `Tx` or `TxRaw` bytes, given as hex, base64 or binary
+    Decode {
+        /// `@file`, `-` for stdin, or the encoded bytes
+        tx: String,
+        /// verifies the signature, along with `--account-number`
+        #[clap(long, requires = "account_number")]
+        chain_id: Option<String>,
+        #[clap(long, requires = "chain_id")]
+        account_number: Option<u64>,
+    },
     AddAccount {
         #[clap(value_parser(custom_keystorebackend))]
         keystore: KeyStoreBackend,
         key: String,
         #[clap(value_enum)]
         addr_type: AddressType,
+        /// defaults to 118 for cosmos and 60 for ethereum addresses
+        #[clap(long)]
+        coin_type: Option<u64>,
+        #[clap(flatten)]
+        hd: HdPathArgs,
+    },
+    AddMultisigAccount {
+        key: String,
+        threshold: u32,
+        /// account names or base64 encoded secp256k1 public keys
+        #[clap(required = true)]
+        members: Vec<String>,
+        /// bech32 prefix of the printed address
+        #[clap(long, default_value = "cosmos")]
+        prefix: String,
+    },
+    /// Adds a labelled address to `contacts.yaml`, to use as a counterparty in `tx`
+    AddContact {
+        label: String,
+        address: String,
+    },
+    RemoveContact {
+        label: String,
+    },
+    Contacts,
+    /// Finds the open transfer channel from a chain to another in `chains.yaml`
+    IbcChannel {
+        chain_id: String,
+        to_chain: String,
+        #[clap(long, short)]
+        rpc: Option<String>,
+    },
+    MultisigSign {
+        file: String,
+        signer: String,
+        output: Option<String>,
+    },
+    MultisigCombine {
+        file: String,
+        multisig: String,
+        #[clap(required = true)]
+        signatures: Vec<String>,
+        #[clap(long, short)]
+        output: Option<String>,
+        #[clap(long, short)]
+        rpc: Option<String>,
+        #[clap(flatten)]
+        broadcast: BroadcastArgs,
     },
     AddChain {
         chain_id: String,
@@ -67,6 +163,35 @@ pub enum Args {
         key: String,
         #[clap(default_value_t = 118)]
         coin_type: u64,
+        #[clap(flatten)]
+        hd: HdPathArgs,
+        /// prompt for a BIP39 passphrase (25th word)
+        #[clap(long)]
+        passphrase: bool,
+    },
+    AddKeyToFile {
+        key: String,
+        #[clap(default_value_t = 118)]
+        coin_type: u64,
+        #[clap(flatten)]
+        hd: HdPathArgs,
+        /// prompt for a BIP39 passphrase (25th word)
+        #[clap(long)]
+        passphrase: bool,
+    },
+    ImportKey {
+        #[clap(value_parser(custom_keystorebackend))]
+        keystore: KeyStoreBackend,
+        #[clap(value_parser(custom_io_string))]
+        armor: String,
+    },
+    ExportKey {
+        #[clap(value_parser(custom_keystorebackend))]
+        keystore: KeyStoreBackend,
+        output: Option<String>,
+        /// key type of the armor, `ethereum` or `injective` for `eth_secp256k1` keys
+        #[clap(long, value_enum, default_value_t)]
+        addr_type: AddressType,
     },
     Vanity {
         prefix: String,
@@ -138,20 +263,22 @@ impl Args {
             }
             Self::RefreshEndpoint { .. } => todo!(),
             Self::Tx {
-                dry_run,
+                broadcast,
                 chain_id,
                 executor,
                 transaction,
                 rpc,
-                fee,
+                options,
+                output,
             } => {
                 transaction
                     .run(
-                        *dry_run,
+                        broadcast,
                         chain_id,
                         executor.as_deref(),
                         rpc.as_deref(),
-                        fee.as_ref(),
+                        options,
+                        output,
                     )
                     .await
             }
@@ -159,6 +286,36 @@ impl Args {
                 keystore,
                 key,
                 addr_type,
+                coin_type,
+                hd,
+            } => {
+                let project_dir = directories::ProjectDirs::from("systems", "rnbguy", "rover")
+                    .context("project dir")?;
+                let data_local_dir = project_dir.data_local_dir();
+                std::fs::create_dir_all(data_local_dir)?;
+                let accounts_path = data_local_dir.join("accounts.yaml");
+                let accounts_path_str = accounts_path.to_str().context("project path")?;
+                let mut accounts: HashMap<String, Account> =
+                    read_data_from_yaml(accounts_path_str).unwrap_or_default();
+                // the other keystores hold a key derived when it was added
+                (matches!(keystore, KeyStoreBackend::Ledger)
+                    || (coin_type.is_none() && !hd.is_set()))
+                .then_some(())
+                .context("--coin-type and the HD path flags only apply to Ledger accounts")?;
+                let hd_path =
+                    hd.derivation_path(coin_type.unwrap_or_else(|| addr_type.coin_type()))?;
+                let new_account =
+                    Account::new(keystore.clone(), addr_type, Some(hd_path.to_string())).await?;
+                accounts.insert(key.into(), new_account);
+                write_data_as_yaml(accounts_path_str, accounts)?;
+                println!("Added to {accounts_path_str}");
+                Ok(())
+            }
+            Self::AddMultisigAccount {
+                key,
+                threshold,
+                members,
+                prefix,
             } => {
                 let project_dir = directories::ProjectDirs::from("systems", "rnbguy", "rover")
                     .context("project dir")?;
@@ -168,16 +325,379 @@ impl Args {
                 let accounts_path_str = accounts_path.to_str().context("project path")?;
                 let mut accounts: HashMap<String, Account> =
                     read_data_from_yaml(accounts_path_str).unwrap_or_default();
-                let new_account = Account::new(keystore.clone(), addr_type).await?;
+
+                let mut public_keys = vec![];
+                for member in members {
+                    public_keys.push(match accounts.get(member) {
+                        Some(account) => account.public_key_bytes().await?,
+                        None => BASE64_STANDARD
+                            .decode(member)
+                            .context(format!("{member} is neither an account nor a public key"))?,
+                    });
+                }
+
+                let new_account = Account::new_multisig(*threshold, public_keys)?;
+                println!("Multisig address: {}", new_account.address(prefix)?);
                 accounts.insert(key.into(), new_account);
                 write_data_as_yaml(accounts_path_str, accounts)?;
                 println!("Added to {accounts_path_str}");
                 Ok(())
             }
-            Self::AddKeyToOs { key, coin_type } => {
+            Self::AddContact { label, address } => {
+                let project_dir = directories::ProjectDirs::from("systems", "rnbguy", "rover")
+                    .context("project dir")?;
+                let data_local_dir = project_dir.data_local_dir();
+                std::fs::create_dir_all(data_local_dir)?;
+                let contacts_path = data_local_dir.join("contacts.yaml");
+                let contacts_path_str = contacts_path.to_str().context("project path")?;
+                let mut contacts: HashMap<String, String> =
+                    read_data_from_yaml_or_default(contacts_path_str)?;
+                bech32::decode(address).context(format!("{address} is not a bech32 address"))?;
+                contacts.insert(label.into(), address.into());
+                write_data_as_yaml(contacts_path_str, contacts)?;
+                println!("Added to {contacts_path_str}");
+                Ok(())
+            }
+            Self::RemoveContact { label } => {
+                let project_dir = directories::ProjectDirs::from("systems", "rnbguy", "rover")
+                    .context("project dir")?;
+                let data_local_dir = project_dir.data_local_dir();
+                let contacts_path = data_local_dir.join("contacts.yaml");
+                let contacts_path_str = contacts_path.to_str().context("project path")?;
+                let mut contacts: HashMap<String, String> = read_data_from_yaml(contacts_path_str)?;
+                contacts
+                    .remove(label)
+                    .context(format!("{label} is not in contacts"))?;
+                write_data_as_yaml(contacts_path_str, contacts)?;
+                println!("Removed from {contacts_path_str}");
+                Ok(())
+            }
+            Self::Contacts => {
+                let project_dir = directories::ProjectDirs::from("systems", "rnbguy", "rover")
+                    .context("project dir")?;
+                let data_local_dir = project_dir.data_local_dir();
+                let contacts_path = data_local_dir.join("contacts.yaml");
+                let contacts_path_str = contacts_path.to_str().context("project path")?;
+                let contacts: HashMap<String, String> =
+                    read_data_from_yaml_or_default(contacts_path_str)?;
+                let mut contacts = contacts.into_iter().collect::<Vec<_>>();
+                contacts.sort();
+                for (label, address) in contacts {
+                    println!("{label}: {address}");
+                }
+                Ok(())
+            }
+            Self::IbcChannel {
+                chain_id,
+                to_chain,
+                rpc,
+            } => {
+                let project_dir = directories::ProjectDirs::from("systems", "rnbguy", "rover")
+                    .context("project dir")?;
+                let chains_path = project_dir.data_local_dir().join("chains.yaml");
+                let chains_path_str = chains_path.to_str().context("project path")?;
+                let chains: HashMap<String, crate::chain::Chain> =
+                    read_data_from_yaml(chains_path_str)?;
+                let to_chain = chains
+                    .get(to_chain)
+                    .context(format!("{to_chain} is not in chains.yaml"))?;
+
+                let config_dir = project_dir.config_dir();
+                let config_path = config_dir.join("config.yaml");
+                let config_path_str = config_path.to_str().context("project path")?;
+                let config: HashMap<String, String> = read_data_from_yaml(config_path_str)?;
+                let graphql_endpoint = config.get("graphql").expect("not exists");
+
+                let rpc_endpoints = rpc
+                    .iter()
+                    .cloned()
+                    .chain(
+                        get_rpc_endpoints(chain_id, graphql_endpoint)
+                            .await?
+                            .into_iter()
+                            .map(|(_, rpc_endpoint)| rpc_endpoint),
+                    )
+                    .collect::<Vec<_>>();
+
+                let channel_id = futures::stream::iter(rpc_endpoints)
+                    .then(|rpc_endpoint| async move {
+                        find_transfer_channel(&rpc_endpoint, &to_chain.chain_id).await
+                    })
+                    .filter_map(|x| async { x.ok() })
+                    .boxed_local()
+                    .next()
+                    .await
+                    .context(format!("no open transfer channel to {}", to_chain.chain_id))?;
+
+                println!("{channel_id}");
+                Ok(())
+            }
+            Self::Sign {
+                file,
+                signer,
+                tx_file,
+                output,
+                encoding,
+            } => {
+                let project_dir = directories::ProjectDirs::from("systems", "rnbguy", "rover")
+                    .context("project dir")?;
+                let data_local_dir = project_dir.data_local_dir();
+                let accounts_path = data_local_dir.join("accounts.yaml");
+                let accounts_path_str = accounts_path.to_str().context("project path")?;
+                let accounts: HashMap<String, Account> = read_data_from_yaml(accounts_path_str)?;
+
+                let signer = accounts.get(signer).context("signer is not in accounts")?;
+                let unsigned = tx_file.read(file)?;
+
+                let signed_tx = signer
+                    .sign_unsigned_transaction(
+                        &unsigned.tx,
+                        &unsigned.chain_id,
+                        unsigned.account_number,
+                    )
+                    .await?;
+
+                write_tx_file(
+                    &TxFile {
+                        tx: signed_tx,
+                        ..unsigned
+                    },
+                    output.as_deref(),
+                    *encoding,
+                )
+            }
+            Self::Broadcast {
+                file,
+                chain_id,
+                rpc,
+                broadcast,
+            } => {
+                let project_dir = directories::ProjectDirs::from("systems", "rnbguy", "rover")
+                    .context("project dir")?;
+                let config_dir = project_dir.config_dir();
+                let config_path = config_dir.join("config.yaml");
+                let config_path_str = config_path.to_str().context("project path")?;
+                let config: HashMap<String, String> = read_data_from_yaml(config_path_str)?;
+                let graphql_endpoint = config.get("graphql").expect("not exists");
+
+                let (chain_id, signed_tx): (String, Tx) = match read_data_from_json::<TxFile>(file)
+                {
+                    Ok(tx_file) => (chain_id.clone().unwrap_or(tx_file.chain_id), tx_file.tx),
+                    Err(_) => (
+                        chain_id
+                            .clone()
+                            .context("base64 transactions need --chain-id")?,
+                        read_from_base64(&std::fs::read_to_string(file)?)?,
+                    ),
+                };
+
+                (!signed_tx.signatures.is_empty())
+                    .then_some(())
+                    .context("transaction is not signed")?;
+
+                if broadcast.dry_run {
+                    println!("{}", serde_json::to_string_pretty(&signed_tx)?);
+                    return Ok(());
+                }
+
+                let chains_path = project_dir.data_local_dir().join("chains.yaml");
+                let chains_path_str = chains_path.to_str().context("project path")?;
+                let chains: HashMap<String, crate::chain::Chain> =
+                    read_data_from_yaml(chains_path_str).unwrap_or_default();
+
+                let endpoints = collect_endpoints(
+                    &chain_id,
+                    chains.get(&chain_id),
+                    graphql_endpoint,
+                    rpc.as_deref(),
+                    broadcast,
+                )
+                .await?;
+                broadcast_and_wait(&endpoints, &signed_tx, broadcast).await
+            }
+            Self::Decode {
+                tx,
+                chain_id,
+                account_number,
+            } => {
+                // `TxRaw` shares the wire format with `Tx`
+                let tx: Tx = read_from_bytes(&custom_io_bytes(tx)?)?;
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&inspect_transaction(&tx)?)?
+                );
+
+                if let (Some(chain_id), Some(account_number)) = (chain_id, account_number) {
+                    verify_transaction(&tx, chain_id, *account_number, &signer_public_key(&tx)?)?;
+                    println!("signature is valid");
+                }
+                Ok(())
+            }
+            Self::MultisigSign {
+                file,
+                signer,
+                output,
+            } => {
+                let project_dir = directories::ProjectDirs::from("systems", "rnbguy", "rover")
+                    .context("project dir")?;
+                let data_local_dir = project_dir.data_local_dir();
+                let accounts_path = data_local_dir.join("accounts.yaml");
+                let accounts_path_str = accounts_path.to_str().context("project path")?;
+                let accounts: HashMap<String, Account> = read_data_from_yaml(accounts_path_str)?;
+
+                let signer = accounts.get(signer).context("signer is not in accounts")?;
+                let tx_file: TxFile = read_data_from_json(file)?;
+
+                let partial_signature = signer
+                    .sign_legacy_amino_json(&tx_file.tx, &tx_file.chain_id, tx_file.account_number)
+                    .await?;
+
+                match output {
+                    Some(output) => write_data_as_json(output, partial_signature)?,
+                    None => println!("{}", serde_json::to_string_pretty(&partial_signature)?),
+                }
+                Ok(())
+            }
+            Self::MultisigCombine {
+                file,
+                multisig,
+                signatures,
+                output,
+                rpc,
+                broadcast,
+            } => {
+                let project_dir = directories::ProjectDirs::from("systems", "rnbguy", "rover")
+                    .context("project dir")?;
+                let data_local_dir = project_dir.data_local_dir();
+                let accounts_path = data_local_dir.join("accounts.yaml");
+                let accounts_path_str = accounts_path.to_str().context("project path")?;
+                let accounts: HashMap<String, Account> = read_data_from_yaml(accounts_path_str)?;
+
+                let config_dir = project_dir.config_dir();
+                let config_path = config_dir.join("config.yaml");
+                let config_path_str = config_path.to_str().context("project path")?;
+                let config: HashMap<String, String> = read_data_from_yaml(config_path_str)?;
+                let graphql_endpoint = config.get("graphql").expect("not exists");
+
+                let (threshold, public_keys) = accounts
+                    .get(multisig)
+                    .context("multisig is not in accounts")?
+                    .multisig()?
+                    .context("not a multisig account")?;
+
+                let tx_file: TxFile = read_data_from_json(file)?;
+                let partial_signatures = signatures
+                    .iter()
+                    .map(|signature| read_data_from_json(signature))
+                    .collect::<Result<Vec<PartialSignature>>>()?;
+
+                let sign_bytes = serde_json::to_vec(&generate_legacy_amino_json(
+                    &tx_file.tx,
+                    &tx_file.chain_id,
+                    tx_file.account_number,
+                )?)?;
+
+                let signed_tx = combine_signatures(
+                    &tx_file.tx,
+                    threshold,
+                    &public_keys,
+                    &partial_signatures,
+                    &sign_bytes,
+                )?;
+
+                println!("{}", serde_json::to_string_pretty(&signed_tx)?);
+
+                if let Some(output) = output {
+                    write_data_as_json(
+                        output,
+                        TxFile {
+                            tx: signed_tx.clone(),
+                            ..tx_file.clone()
+                        },
+                    )?;
+                }
+
+                if !broadcast.dry_run {
+                    let chains_path = data_local_dir.join("chains.yaml");
+                    let chains_path_str = chains_path.to_str().context("project path")?;
+                    let chains: HashMap<String, crate::chain::Chain> =
+                        read_data_from_yaml(chains_path_str).unwrap_or_default();
+
+                    let endpoints = collect_endpoints(
+                        &tx_file.chain_id,
+                        chains.get(&tx_file.chain_id),
+                        graphql_endpoint,
+                        rpc.as_deref(),
+                        broadcast,
+                    )
+                    .await?;
+                    broadcast_and_wait(&endpoints, &signed_tx, broadcast).await?;
+                }
+                Ok(())
+            }
+            Self::AddKeyToOs {
+                key,
+                coin_type,
+                hd,
+                passphrase,
+            } => {
+                let mmseed =
+                    rpassword::prompt_password("Mnemonic 🔑: ").context("unable to read")?;
+                let passphrase = bip39_passphrase(*passphrase)?;
+                save_key_to_os_from_mmseed(
+                    mmseed.trim(),
+                    key,
+                    &hd.derivation_path(*coin_type)?,
+                    &passphrase,
+                )?;
+                Ok(())
+            }
+            Self::AddKeyToFile {
+                key,
+                coin_type,
+                hd,
+                passphrase,
+            } => {
                 let mmseed =
                     rpassword::prompt_password("Mnemonic 🔑: ").context("unable to read")?;
-                save_key_to_os_from_mmseed(mmseed.trim(), key, *coin_type)?;
+                let passphrase = bip39_passphrase(*passphrase)?;
+                save_key_to_file_from_mmseed(
+                    mmseed.trim(),
+                    key,
+                    &hd.derivation_path(*coin_type)?,
+                    &passphrase,
+                )?;
+                Ok(())
+            }
+            Self::ImportKey { keystore, armor } => {
+                let passphrase = rpassword::prompt_password("Armor passphrase 🔒: ")
+                    .context("unable to read")?;
+                let (priv_bytes, addr_type) =
+                    crate::armor::unarmor_decrypt_priv_key(armor, &passphrase)?;
+                keystore.save_private_key(&priv_bytes)?;
+                if addr_type != AddressType::Cosmos {
+                    let addr_type = format!("{addr_type:?}").to_lowercase();
+                    println!("an eth_secp256k1 key, add its account as an {addr_type} account");
+                }
+                Ok(())
+            }
+            Self::ExportKey {
+                keystore,
+                output,
+                addr_type,
+            } => {
+                let priv_key = keystore.private_key()?;
+                let passphrase = rpassword::prompt_password("Armor passphrase 🔒: ")
+                    .context("unable to read")?;
+                let armor = crate::armor::encrypt_armor_priv_key(
+                    &priv_key.to_bytes(),
+                    &passphrase,
+                    addr_type,
+                )?;
+                match output {
+                    Some(output) => crate::utils::write_private_file(output, armor.as_bytes())?,
+                    None => println!("{armor}"),
+                }
                 Ok(())
             }
             Self::Config { key, value } => {
@@ -220,6 +740,7 @@ impl Args {
                         prefix: prefix.into(),
                         fee: *fee,
                         denom: denom.into(),
+                        ..Default::default()
                     },
                 );
                 write_data_as_yaml(chains_path_str, chains)?;
@@ -373,6 +894,7 @@ impl Args {
                             prefix: prefix.into(),
                             fee: *fee,
                             denom: denom.into(),
+                            ..Default::default()
                         },
                     );
                 }
@@ -416,6 +938,24 @@ impl Args {
                         .and_then(|x| x.as_str());
                     let fee = &0;
                     let denom = cd_chain.pointer("/denom").and_then(|x| x.as_str());
+                    let gas_prices = data
+                        .pointer("/chain/fees/fee_tokens")
+                        .and_then(|x| x.as_array())
+                        .map(|fee_tokens| {
+                            fee_tokens
+                                .iter()
+                                .filter_map(|fee_token| {
+                                    let price = |key: &str| fee_token.get(key)?.as_f64();
+                                    Some(crate::chain::GasPrices {
+                                        denom: fee_token.get("denom")?.as_str()?.into(),
+                                        low: price("low_gas_price")?,
+                                        average: price("average_gas_price")?,
+                                        high: price("high_gas_price")?,
+                                    })
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default();
 
                     match (chain_id, prefix, denom) {
                         (Some(chain_id), Some(prefix), Some(denom)) => {
@@ -426,6 +966,8 @@ impl Args {
                                     prefix: prefix.into(),
                                     fee: *fee,
                                     denom: denom.into(),
+                                    gas_prices,
+                                    ..Default::default()
                                 },
                             );
                         }
@@ -514,6 +1056,7 @@ impl Args {
                                 prefix: prefix.into(),
                                 fee: *fee,
                                 denom: denom.into(),
+                                ..Default::default()
                             },
                         );
                     } else {
@@ -1,24 +1,433 @@
 use std::collections::HashMap;
+use std::future::Future;
+use std::str::FromStr;
 
 use anyhow::Context;
 use clap::Subcommand;
+use cosmos_sdk_proto::cosmos::bank::v1beta1::MsgSend;
 use cosmos_sdk_proto::cosmos::base::v1beta1::Coin;
-use cosmos_sdk_proto::cosmos::gov::v1beta1::MsgVote;
-use cosmos_sdk_proto::cosmos::staking::v1beta1::{MsgBeginRedelegate, MsgDelegate};
+use cosmos_sdk_proto::cosmos::crypto::multisig::LegacyAminoPubKey;
+use cosmos_sdk_proto::cosmos::distribution::v1beta1::{
+    MsgSetWithdrawAddress, MsgWithdrawValidatorCommission,
+};
+use cosmos_sdk_proto::cosmos::gov::{v1, v1beta1};
+use cosmos_sdk_proto::cosmos::slashing::v1beta1::MsgUnjail;
+use cosmos_sdk_proto::cosmos::staking::v1beta1::{
+    CommissionRates, MsgBeginRedelegate, MsgCancelUnbondingDelegation, MsgCreateValidator,
+    MsgDelegate, MsgEditValidator, MsgUndelegate,
+};
+use cosmos_sdk_proto::cosmos::tx::v1beta1::Tx;
 use cosmos_sdk_proto::cosmwasm::wasm::v1::MsgExecuteContract;
-use cosmos_sdk_proto::ibc::applications::transfer::v1::MsgTransfer;
+use cosmos_sdk_proto::ibc::core::client::v1::Height;
 use cosmos_sdk_proto::prost_wkt_types::Any;
 use futures::StreamExt;
+use serde::Deserialize;
 
-use super::utils::{custom_coin, custom_io_string, VotePair};
+use super::utils::{
+    custom_coin, custom_io_string, deserialize_coin, BroadcastArgs, Gas, IbcHop, PayoutRows,
+    TxArgs, TxOutputArgs, ValidatorDescription, VotePair, WeightedVote,
+};
 use crate::account::Account;
-use crate::endpoint::{get_cosmos_directory_name, get_rpc_endpoints};
-use crate::msg::generate_grant_exec;
-use crate::txs::get_account_number_and_sequence;
-use crate::utils::read_data_from_yaml;
+use crate::broadcast::{CheckTxResult, Endpoints, TxResult};
+use crate::chain::FeeTier;
+use crate::endpoint::{get_cosmos_directory_chain_name, get_rpc_endpoints};
+use crate::keys::read_consensus_public_key;
+use crate::msg::{forward_memo, generate_grant_exec, ibc_transfer};
+use crate::multisig::placeholder_multi_signature;
+use crate::packet::{track_packet, SendPacket};
+use crate::query::{
+    find_transfer_channel, get_accepted_fee_denoms, get_all_balances, get_base_gas_price,
+    get_channel_client_state, get_counterparty_height, get_latest_height_rpc, get_voting_proposals,
+    has_voted, resolve_ibc_denom, supports_gov_v1,
+};
+use crate::sequence::SequenceTracker;
+use crate::txs::{
+    fee_from_gas_price, get_account_number_and_sequence, get_sequence, set_sequence,
+    update_tx_with_fee, TxFile, TxOptions,
+};
+use crate::utils::{bech32, parse_legacy_dec, read_data_from_yaml, read_data_from_yaml_or_default};
 use crate::Result;
 
-#[derive(Subcommand, Debug)]
+pub async fn collect_endpoints(
+    chain_id: &str,
+    chain: Option<&crate::chain::Chain>,
+    graphql_endpoint: &str,
+    rpc: Option<&str>,
+    broadcast: &BroadcastArgs,
+) -> Result<Endpoints> {
+    let mut endpoints = Endpoints {
+        preferred: broadcast
+            .via
+            .or(chain.map(|chain| chain.broadcast))
+            .unwrap_or_default(),
+        rpc: get_rpc_endpoints(chain_id, graphql_endpoint)
+            .await?
+            .into_iter()
+            .map(|(_, rpc_endpoint)| rpc_endpoint)
+            .collect(),
+        rest: broadcast.rest.iter().cloned().collect(),
+        grpc: broadcast.grpc.iter().cloned().collect(),
+    };
+
+    if let Some(rpc_endpoint) = rpc {
+        endpoints.rpc.push(rpc_endpoint.into())
+    }
+
+    if let Some(chain) = chain {
+        endpoints.rest.extend(chain.rest_endpoints.iter().cloned());
+        endpoints.grpc.extend(chain.grpc_endpoints.iter().cloned());
+    }
+
+    if let Ok(cosmos_directory_name) = get_cosmos_directory_chain_name(chain_id).await {
+        endpoints.rpc.push(format!(
+            "https://rpc.cosmos.directory/{cosmos_directory_name}"
+        ));
+        endpoints.rest.push(format!(
+            "https://rest.cosmos.directory/{cosmos_directory_name}"
+        ));
+    }
+
+    Ok(endpoints)
+}
+
+/// Broadcasts via the first working endpoint, then waits for the transaction
+/// to be included in a block, unless `--wait-timeout` is zero.
+pub async fn broadcast_and_wait(
+    endpoints: &Endpoints,
+    signed_tx: &Tx,
+    broadcast: &BroadcastArgs,
+) -> Result<()> {
+    let resp = endpoints.broadcast(signed_tx).await?;
+
+    wait_for_inclusion(endpoints, &resp, broadcast).await?;
+
+    Ok(())
+}
+
+/// Waits for a transaction accepted by `CheckTx` and checks its `DeliverTx` result,
+/// which is returned unless `--wait-timeout` is zero.
+pub async fn wait_for_inclusion(
+    endpoints: &Endpoints,
+    resp: &CheckTxResult,
+    broadcast: &BroadcastArgs,
+) -> Result<Option<TxResult>> {
+    resp.is_ok().then_some(()).context(format!(
+        "transaction rejected with code {} ({}): {}",
+        resp.code, resp.codespace, resp.raw_log
+    ))?;
+
+    if broadcast.wait_timeout == 0 {
+        return Ok(None);
+    }
+
+    println!("waiting for {} to be included", resp.hash);
+
+    let tx_result = endpoints
+        .wait_for_tx(
+            &resp.hash,
+            std::time::Duration::from_secs(broadcast.wait_timeout),
+        )
+        .await?;
+
+    println!("{}", serde_json::to_string_pretty(&tx_result)?);
+
+    tx_result.is_ok().then_some(()).context(format!(
+        "transaction failed with code {} ({}): {}",
+        tx_result.code, tx_result.codespace, tx_result.raw_log
+    ))?;
+
+    Ok(Some(tx_result))
+}
+
+/// Simulates an unsigned transaction with a placeholder signature, as the
+/// nodes skip signature verification while simulating. Multisig signers get a
+/// placeholder signature for each member up to the threshold.
+pub async fn estimate_gas(endpoints: &Endpoints, unsigned_tx: &Tx) -> Option<u64> {
+    let multisig_public_key = unsigned_tx
+        .auth_info
+        .as_ref()?
+        .signer_infos
+        .first()?
+        .public_key
+        .clone()
+        .and_then(|public_key| public_key.unpack_as(LegacyAminoPubKey::default()).ok());
+    let placeholder_tx = match multisig_public_key {
+        Some(public_key) => placeholder_multi_signature(unsigned_tx, &public_key).ok()?,
+        None => crate::txs::update_signature(unsigned_tx.clone(), &[0; 64]),
+    };
+    endpoints.simulate(&placeholder_tx).await.ok()
+}
+
+/// Fee denoms with their gas prices, starting with the chain denom. The
+/// others come from the chain config and the fee modules of the chain.
+pub async fn fee_denom_candidates(
+    endpoints: &Endpoints,
+    chain: &crate::chain::Chain,
+    fee_tier: FeeTier,
+    native_gas_price: Option<f64>,
+) -> Vec<(String, Option<f64>)> {
+    let mut candidates = vec![(chain.denom.clone(), native_gas_price)];
+
+    for gas_prices in &chain.gas_prices {
+        if !candidates
+            .iter()
+            .any(|(denom, _)| denom == &gas_prices.denom)
+        {
+            candidates.push((gas_prices.denom.clone(), Some(gas_prices.price(fee_tier))));
+        }
+    }
+
+    let accepted_fee_denoms = futures::stream::iter(endpoints.rpc.iter().take(3))
+        .then(|rpc_endpoint| get_accepted_fee_denoms(rpc_endpoint))
+        .filter_map(|x| async { x.ok() })
+        .boxed_local()
+        .next()
+        .await
+        .unwrap_or_default();
+
+    for (denom, min_gas_price) in accepted_fee_denoms {
+        match candidates.iter_mut().find(|(x, _)| x == &denom) {
+            // minimum gas prices of the chain win over stale configs
+            Some((_, gas_price)) => {
+                *gas_price = match (*gas_price, min_gas_price) {
+                    (Some(gas_price), Some(min_gas_price)) => Some(gas_price.max(min_gas_price)),
+                    (gas_price, min_gas_price) => gas_price.or(min_gas_price),
+                }
+            }
+            None => candidates.push((denom, min_gas_price)),
+        }
+    }
+
+    candidates
+}
+
+/// First fee denom which the payer holds enough of for the fee.
+pub async fn select_fee_denom(
+    endpoints: &Endpoints,
+    chain: &crate::chain::Chain,
+    payer: &str,
+    candidates: &[(String, Option<f64>)],
+    gas_limit: u64,
+) -> Option<(String, Option<f64>)> {
+    let balances = futures::stream::iter(endpoints.rpc.iter())
+        .then(|rpc_endpoint| get_all_balances(payer, rpc_endpoint))
+        .filter_map(|x| async { x.ok() })
+        .boxed_local()
+        .next()
+        .await?;
+
+    candidates
+        .iter()
+        .find(|(denom, gas_price)| {
+            let needed = match gas_price {
+                Some(gas_price) => fee_from_gas_price(gas_limit, *gas_price),
+                None if denom == &chain.denom => chain.fee,
+                None => return false,
+            };
+            balances
+                .iter()
+                .any(|(x, amount)| x == denom && *amount >= needed)
+        })
+        .cloned()
+}
+
+/// Address of a rover account, a `contacts.yaml` label or a bech32 address,
+/// and whether it is on `prefix`.
+fn lookup_address(
+    name: &str,
+    accounts: &HashMap<String, Account>,
+    contacts: &HashMap<String, String>,
+    prefix: &str,
+) -> Result<(String, bool)> {
+    if let Some(account) = accounts.get(name) {
+        return Ok((account.address(prefix)?, true));
+    }
+
+    let address = contacts.get(name).map_or(name, String::as_str);
+
+    let (hrp, _) = bech32::decode(address).context(format!(
+        "{name} is neither an account, a contact nor an address"
+    ))?;
+
+    Ok((address.into(), hrp.as_str() == prefix))
+}
+
+/// Address of a counterparty, which is a rover account, a `contacts.yaml` label
+/// or a bech32 address. Addresses with other prefixes are converted to `prefix`.
+pub fn resolve_address(
+    name: &str,
+    accounts: &HashMap<String, Account>,
+    contacts: &HashMap<String, String>,
+    prefix: &str,
+) -> Result<String> {
+    let (address, on_prefix) = lookup_address(name, accounts, contacts, prefix)?;
+
+    if on_prefix {
+        return Ok(address);
+    }
+
+    let converted = bech32(&address, prefix)?;
+    println!("warning: {address} is not a {prefix} address, using {converted}");
+    Ok(converted)
+}
+
+/// Payout rows as coins, with their addresses resolved on the chain. Unlike
+/// counterparties, addresses with other prefixes are refused, as they may
+/// belong to another chain.
+pub fn payout_outputs(
+    rows: &PayoutRows,
+    accounts: &HashMap<String, Account>,
+    contacts: &HashMap<String, String>,
+    chain: &crate::chain::Chain,
+) -> Result<Vec<(String, Coin)>> {
+    rows.0
+        .iter()
+        .map(|(address, amount, denom)| {
+            let (resolved, on_prefix) = lookup_address(address, accounts, contacts, &chain.prefix)?;
+            on_prefix
+                .then_some(())
+                .context(format!("{resolved} is not a {} address", chain.prefix))?;
+            Ok((
+                resolved,
+                Coin {
+                    denom: denom.clone().unwrap_or_else(|| chain.denom.clone()),
+                    amount: amount.to_string(),
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Prints the payout totals, and asks for a confirmation unless `yes`.
+pub fn confirm_payout(
+    rows: &PayoutRows,
+    accounts: &HashMap<String, Account>,
+    contacts: &HashMap<String, String>,
+    chain: &crate::chain::Chain,
+    chunk_size: Option<usize>,
+    yes: bool,
+) -> Result<()> {
+    (chunk_size != Some(0))
+        .then_some(())
+        .context("--chunk-size should be positive")?;
+
+    let outputs = payout_outputs(rows, accounts, contacts, chain)?;
+
+    let mut totals = std::collections::BTreeMap::<&str, u128>::new();
+    for (_, coin) in &outputs {
+        *totals.entry(coin.denom.as_str()).or_default() += coin.amount.parse::<u128>()?;
+    }
+
+    println!("paying {} rows", outputs.len());
+    for (denom, amount) in &totals {
+        println!("  {amount}{denom}");
+    }
+    match chunk_size {
+        Some(chunk_size) => println!(
+            "in {} transactions of up to {chunk_size} MsgSend",
+            outputs.len().div_ceil(chunk_size)
+        ),
+        None => println!("in one MsgMultiSend"),
+    }
+
+    if yes {
+        return Ok(());
+    }
+
+    let question = requestty::Question::confirm("payout")
+        .message("sign and send the payout?")
+        .default(false)
+        .build();
+
+    requestty::prompt_one(question)?
+        .as_bool()
+        .unwrap_or_default()
+        .then_some(())
+        .context("payout is cancelled")
+}
+
+fn default_min_self_delegation() -> String {
+    "1".into()
+}
+
+/// Whether `err` comes from reaching the endpoint, rather than from the
+/// queried state.
+fn is_transport_error(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        cause.is::<tendermint_rpc::Error>()
+            || matches!(cause.downcast_ref(), Some(ureq::Error::Transport(_)))
+    })
+}
+
+/// Chunks the messages in order, so that the gas of each chunk, summed over
+/// its messages simulated one by one, stays under `max_gas`. A message above
+/// `max_gas` on its own gets a chunk of its own.
+pub async fn split_by_gas<F, Fut>(
+    endpoints: &Endpoints,
+    any_msgs: Vec<Any>,
+    max_gas: u64,
+    generate_unsigned_transaction: F,
+) -> Result<Vec<Vec<Any>>>
+where
+    F: Fn(Vec<Any>) -> Fut,
+    Fut: Future<Output = Result<(u64, Tx)>>,
+{
+    let mut msgs_with_gas = vec![];
+
+    for any_msg in any_msgs {
+        let (_, unsigned_tx) = generate_unsigned_transaction(vec![any_msg.clone()]).await?;
+        let needed_gas = estimate_gas(endpoints, &unsigned_tx)
+            .await
+            .context("not able to simulate the message")?;
+        msgs_with_gas.push((any_msg, needed_gas));
+    }
+
+    let chunks = chunk_by_gas(msgs_with_gas, max_gas);
+
+    println!("split into {} transactions", chunks.len());
+
+    Ok(chunks)
+}
+
+/// Chunks of [`split_by_gas`], from the gas of each message.
+fn chunk_by_gas<T>(msgs_with_gas: Vec<(T, u64)>, max_gas: u64) -> Vec<Vec<T>> {
+    let mut chunks = vec![];
+    let mut chunk = vec![];
+    let mut chunk_gas = 0;
+
+    for (msg, needed_gas) in msgs_with_gas {
+        if chunk_gas + needed_gas > max_gas && !chunk.is_empty() {
+            chunks.push(std::mem::take(&mut chunk));
+            chunk_gas = 0;
+        }
+        chunk.push(msg);
+        chunk_gas += needed_gas;
+    }
+
+    if !chunk.is_empty() {
+        chunks.push(chunk);
+    }
+
+    chunks
+}
+
+/// Messages of `tx batch`, in the YAML (or JSON) form of the other `tx`
+/// subcommands, e.g. `{send: {source: my_account, target: alice, amount: 1000}}`.
+/// Entries are externally tagged, as serde buffers internally tagged ones
+/// without `u128` support.
+#[derive(Debug, Clone)]
+pub struct BatchPlan(pub Vec<Transaction>);
+
+impl FromStr for BatchPlan {
+    type Err = anyhow::Error;
+
+    fn from_str(path: &str) -> Result<Self> {
+        Ok(Self(read_data_from_yaml(path)?))
+    }
+}
+
+#[derive(Subcommand, Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum Transaction {
     Send {
         source: String,
@@ -39,6 +448,61 @@ pub enum Transaction {
         target: String,
         amount: Option<u128>,
     },
+    /// undelegates, by default all of the delegation
+    Undelegate {
+        account: String,
+        validator: String,
+        amount: Option<u128>,
+    },
+    /// cancels an unbonding, by default the latest one and all of its balance
+    CancelUnbonding {
+        account: String,
+        validator: String,
+        amount: Option<u128>,
+        #[clap(long)]
+        creation_height: Option<i64>,
+    },
+    SetWithdrawAddress {
+        account: String,
+        withdraw_address: String,
+    },
+    /// withdraws the commission of the validator operated by the account
+    WithdrawCommission {
+        account: String,
+    },
+    Unjail {
+        account: String,
+    },
+    /// creates a validator with the consensus key of a `priv_validator_key.json`
+    CreateValidator {
+        account: String,
+        /// self delegation
+        amount: u128,
+        #[clap(long)]
+        priv_validator_key: String,
+        #[clap(flatten)]
+        #[serde(default)]
+        description: ValidatorDescription,
+        #[clap(long)]
+        commission_rate: String,
+        #[clap(long)]
+        commission_max_rate: String,
+        #[clap(long)]
+        commission_max_change_rate: String,
+        #[clap(long, default_value = "1")]
+        #[serde(default = "default_min_self_delegation")]
+        min_self_delegation: String,
+    },
+    EditValidator {
+        account: String,
+        #[clap(flatten)]
+        #[serde(default)]
+        description: ValidatorDescription,
+        #[clap(long)]
+        commission_rate: Option<String>,
+        #[clap(long)]
+        min_self_delegation: Option<String>,
+    },
     RestakeApp {
         granter: String,
         grantee: String,
@@ -51,41 +515,143 @@ pub enum Transaction {
     Grant {
         granter: String,
         grantee: String,
+        /// also grants the validator operator messages
+        #[clap(long)]
+        #[serde(default)]
+        operator: bool,
     },
     Revoke {
         granter: String,
         grantee: String,
+        /// also revokes the validator operator messages
+        #[clap(long)]
+        #[serde(default)]
+        operator: bool,
     },
     Vote {
         voter: String,
         #[clap(required = true)]
         votes: Vec<VotePair>,
     },
+    /// votes on the proposals in voting period which are not voted on yet
+    VoteAll {
+        voter: String,
+        /// vote for the proposals without one in `--vote`, like `Abstain`
+        #[clap(long)]
+        #[serde(default)]
+        default: Option<WeightedVote>,
+        /// vote for a proposal, like `12:Yes=0.7,Abstain=0.3`
+        #[clap(long = "vote")]
+        #[serde(default)]
+        votes: Vec<VotePair>,
+    },
+    /// deposits to a proposal
+    Deposit {
+        depositor: String,
+        proposal_id: u64,
+        #[clap(value_parser(custom_coin))]
+        #[serde(deserialize_with = "deserialize_coin")]
+        amount: Coin,
+    },
+    /// submits a proposal, with gov v1 messages in proto JSON
+    SubmitProposal {
+        proposer: String,
+        #[clap(long)]
+        title: String,
+        #[clap(long)]
+        summary: String,
+        #[clap(long, default_value = "")]
+        #[serde(default)]
+        metadata: String,
+        /// proto JSON messages, like `tx raw`
+        #[clap(long, value_parser(custom_io_string))]
+        messages: Option<String>,
+        /// initial deposit
+        #[clap(long, value_parser(custom_coin))]
+        #[serde(default, deserialize_with = "deserialize_coin")]
+        deposit: Option<Coin>,
+    },
+    #[serde(rename = "ibc-transfer")]
     IBCTransfer {
-        source_channel: String,
+        #[clap(long, required_unless_present = "to_chain")]
+        #[serde(default)]
+        source_channel: Option<String>,
+        /// chain id in chains.yaml, to use the open transfer channel to it
+        #[clap(long, conflicts_with = "source_channel")]
+        #[serde(default)]
+        to_chain: Option<String>,
+        /// amount in a base denom, a full denom trace or `ibc/HASH`
         #[clap(value_parser(custom_coin))]
+        #[serde(deserialize_with = "deserialize_coin")]
         token: Coin,
         sender: String,
+        /// account, contact or address on the destination chain
         receiver_address: String,
+        /// memo, e.g. packet-forward-middleware or IBC hooks JSON
+        #[clap(long)]
+        #[serde(default)]
+        memo: Option<String>,
+        /// timeout in blocks past the latest height of the counterparty client,
+        /// instead of the 10 minutes timestamp timeout
+        #[clap(long)]
+        #[serde(default)]
+        timeout_height: Option<u64>,
+        /// `chain-id:channel-N` hops to forward the packet over, in order
+        #[clap(long = "hop")]
+        #[serde(default)]
+        hops: Vec<IbcHop>,
+        /// seconds to track the packet until it is acknowledged or timed out
+        #[clap(long)]
+        #[serde(default)]
+        track: Option<u64>,
     },
     Cosmwasm {
         #[clap(value_parser(custom_coin))]
+        #[serde(deserialize_with = "deserialize_coin")]
         funds: Coin,
         sender: String,
         contract_address: String,
         #[clap(value_parser(custom_io_string))]
         json: String,
     },
+    /// sends proto JSON messages with `@type`, or JSON arrays of them
+    Raw {
+        signer: String,
+        #[clap(value_parser(custom_io_string), required = true)]
+        messages: Vec<String>,
+    },
+    /// pays the rows of an `address,amount,denom` CSV file
+    Payout {
+        source: String,
+        csv: PayoutRows,
+        /// sends with this many `MsgSend`s per transaction, instead of one `MsgMultiSend`
+        #[clap(long)]
+        #[serde(default)]
+        chunk_size: Option<usize>,
+        /// skips the confirmation
+        #[clap(long, short)]
+        #[serde(default)]
+        yes: bool,
+    },
+    /// packs the messages of a plan file into one transaction
+    #[serde(skip_deserializing)]
+    Batch {
+        plan: BatchPlan,
+        /// splits the messages into several transactions of at most this much gas
+        #[clap(long)]
+        split: Option<u64>,
+    },
 }
 
 impl Transaction {
     pub async fn run(
         &self,
-        dry_run: bool,
+        broadcast: &BroadcastArgs,
         chain_id: &str,
         executor: Option<&str>,
         rpc: Option<&str>,
-        fee: Option<&Coin>,
+        options: &TxArgs,
+        output: &TxOutputArgs,
     ) -> crate::Result<()> {
         let project_dir =
             directories::ProjectDirs::from("systems", "rnbguy", "rover").context("project dir")?;
@@ -96,6 +662,11 @@ impl Transaction {
 
         let accounts: HashMap<String, Account> = read_data_from_yaml(accounts_path_str)?;
 
+        let contacts_path = data_local_dir.join("contacts.yaml");
+        let contacts_path_str = contacts_path.to_str().context("project path")?;
+
+        let contacts: HashMap<String, String> = read_data_from_yaml_or_default(contacts_path_str)?;
+
         let chains_path = data_local_dir.join("chains.yaml");
         let chains_path_str = chains_path.to_str().context("project path")?;
 
@@ -114,447 +685,1207 @@ impl Transaction {
         let chain = chains.get(chain_id).expect("no chain?");
 
         let hrp = chain.prefix.as_str();
-        let fee = match fee {
-            Some(Coin { denom, amount }) => (amount.parse::<u128>()?, denom.as_str()),
-            None => (chain.fee, chain.denom.as_str()),
+        let endpoints =
+            collect_endpoints(chain_id, Some(chain), graphql_endpoint, rpc, broadcast).await?;
+
+        let tx_options = TxOptions {
+            memo: options.memo.clone(),
+            timeout_height: match (options.timeout_height, options.timeout_blocks) {
+                (Some(timeout_height), _) => timeout_height,
+                (None, Some(timeout_blocks)) => {
+                    futures::stream::iter(endpoints.rpc.iter())
+                        .then(|rpc_endpoint| get_latest_height_rpc(rpc_endpoint))
+                        .filter_map(|x| async { x.ok() })
+                        .boxed_local()
+                        .next()
+                        .await
+                        .context("not able to get the latest height")?
+                        + timeout_blocks
+                }
+                (None, None) => 0,
+            },
+            gas_limit: match options.gas {
+                Gas::Limit(gas_limit) => gas_limit,
+                Gas::Auto => TxOptions::default().gas_limit,
+            },
         };
-        let denom = chain.denom.as_str();
-        let fee_granter = String::new();
-        let mut rpc_endpoints = get_rpc_endpoints(chain_id, graphql_endpoint).await?;
 
-        if let Some(rpc_endpoint) = rpc {
-            rpc_endpoints.push((0, rpc_endpoint.into()))
+        let gas_price = match (&options.gas_prices, &options.fee) {
+            (Some((gas_price, denom)), _) => Some((*gas_price, denom.as_str())),
+            (None, Some(_)) => None,
+            (None, None) => {
+                // the base fee of fee market chains is the floor of the configured price
+                let base_gas_price = if chain.fee_market {
+                    futures::stream::iter(endpoints.rpc.iter().take(3))
+                        .then(|rpc_endpoint| get_base_gas_price(rpc_endpoint, &chain.denom))
+                        .filter_map(|x| async { x.ok() })
+                        .boxed_local()
+                        .next()
+                        .await
+                } else {
+                    None
+                };
+                match (
+                    chain.gas_price(&chain.denom, options.fee_tier),
+                    base_gas_price,
+                ) {
+                    (Some(gas_price), Some(base_gas_price)) => Some(gas_price.max(base_gas_price)),
+                    (gas_price, base_gas_price) => gas_price.or(base_gas_price),
+                }
+                .map(|gas_price| (gas_price, chain.denom.as_str()))
+            }
+        };
+        let (fee, gas_price) = match (&options.fee, gas_price) {
+            (Some(Coin { denom, amount }), _) => ((amount.parse::<u128>()?, denom.as_str()), None),
+            (None, Some((gas_price, denom))) => (
+                (fee_from_gas_price(tx_options.gas_limit, gas_price), denom),
+                Some(gas_price),
+            ),
+            (None, None) => ((chain.fee, chain.denom.as_str()), None),
+        };
+
+        if let Self::IBCTransfer { track: Some(_), .. } = self {
+            (broadcast.wait_timeout > 0)
+                .then_some(())
+                .context("--track needs a --wait-timeout above zero")?;
         }
 
-        if let Ok(cosmos_directory_rpc) = get_cosmos_directory_name(chain_id).await {
-            rpc_endpoints.push((0, cosmos_directory_rpc))
+        // payouts of a batch plan are confirmed too, and packed with the other messages
+        let entries: Vec<&Self> = match self {
+            Self::Batch { plan, .. } => plan.0.iter().collect(),
+            _ => vec![self],
+        };
+        for entry in entries {
+            if let Self::Payout {
+                csv,
+                chunk_size,
+                yes,
+                ..
+            } = entry
+            {
+                if let Self::Batch { .. } = self {
+                    chunk_size
+                        .is_none()
+                        .then_some(())
+                        .context("chunk-size is not supported for payouts in a batch plan")?;
+                }
+                confirm_payout(csv, &accounts, &contacts, chain, *chunk_size, *yes)?;
+            }
         }
 
-        let (owner, account_number, unsigned_tx) = futures::stream::iter(rpc_endpoints.iter())
-            .then(|(_, rpc_endpoint)| {
-                let accounts = accounts.clone();
-                let mut fee_granter = fee_granter.clone();
-                async move {
-                    println!("trying with {}", &rpc_endpoint);
-                    let (mut owner, mut any_msgs) = match &self {
-                        Self::Send {
-                            source: source_key,
-                            target: target_key,
-                            amount,
-                        } => {
-                            let mut any_msgs = vec![];
+        // only an unreachable endpoint is worth trying the next one
+        let (owner, any_msgs) = 'msgs: {
+            let mut last_error = None;
+            for rpc_endpoint in endpoints.rpc.iter() {
+                println!("trying with {}", &rpc_endpoint);
+                match self
+                    .any_msgs(
+                        &accounts,
+                        &contacts,
+                        chain,
+                        fee.0,
+                        rpc_endpoint,
+                        executor.is_some(),
+                    )
+                    .await
+                {
+                    Ok(msgs) => break 'msgs msgs,
+                    Err(err) if is_transport_error(&err) => last_error = Some(err),
+                    Err(err) => return Err(err),
+                }
+            }
+            return Err(last_error
+                .unwrap_or_else(|| anyhow::anyhow!("no rpc endpoint"))
+                .context("not able to create tx"));
+        };
+
+        // the executor signs the messages wrapped in `MsgExec`, and the owner pays the fee
+        let (signer, fee_granter) = match executor {
+            Some(grantee) => (
+                accounts
+                    .get(grantee)
+                    .context(format!("{grantee} is not in accounts"))?,
+                owner.address(hrp)?,
+            ),
+            None => (owner, String::new()),
+        };
+        let signer_address = signer.address(hrp)?;
+        let payer = match fee_granter.as_str() {
+            "" => signer_address.clone(),
+            fee_granter => fee_granter.to_string(),
+        };
+        let (fee_denom, gas_price) = match (&options.fee, &options.gas_prices, &options.fee_denom) {
+            (None, None, Some(fee_denom)) => {
+                let gas_price =
+                    fee_denom_candidates(&endpoints, chain, options.fee_tier, gas_price)
+                        .await
+                        .into_iter()
+                        .find(|(denom, _)| denom == fee_denom)
+                        .and_then(|(_, gas_price)| gas_price)
+                        .context(format!(
+                            "no gas price for {fee_denom}, use --gas-prices <price>{fee_denom} instead of --fee-denom"
+                        ))?;
+                (fee_denom.clone(), Some(gas_price))
+            }
+            (None, None, None) => {
+                let mut candidates =
+                    fee_denom_candidates(&endpoints, chain, options.fee_tier, gas_price).await;
+                // a max fee without --fee-denom is in the chain denom
+                if options.max_fee.or(chain.max_fee).is_some() {
+                    candidates.retain(|(denom, _)| denom == &chain.denom);
+                }
+                select_fee_denom(&endpoints, chain, &payer, &candidates, tx_options.gas_limit)
+                    .await
+                    .unwrap_or((fee.1.to_string(), gas_price))
+            }
+            _ => (fee.1.to_string(), gas_price),
+        };
+        // fees from gas prices follow the gas limit, others are fixed
+        let update_tx_with_gas = |unsigned_tx: Tx, gas_limit: u64| -> Result<Tx> {
+            let unsigned_tx = crate::txs::update_tx_with_gas(unsigned_tx, gas_limit)?;
+            match gas_price {
+                Some(gas_price) => update_tx_with_fee(
+                    unsigned_tx,
+                    (fee_from_gas_price(gas_limit, gas_price), fee_denom.as_str()),
+                ),
+                None => Ok(unsigned_tx),
+            }
+        };
+        // `max_fee` of chains.yaml is in the chain denom, `--max-fee` in the fee denom
+        let max_fee = options
+            .max_fee
+            .or(chain.max_fee.filter(|_| fee_denom == chain.denom));
+        let check_max_fee = |unsigned_tx: &Tx| -> Result<()> {
+            let Some(max_fee) = max_fee else {
+                return Ok(());
+            };
+            let fee_amount = unsigned_tx
+                .auth_info
+                .as_ref()
+                .context("no auth_info")?
+                .fee
+                .as_ref()
+                .context("no fee")?
+                .amount
+                .iter()
+                .filter(|coin| coin.denom == fee_denom.as_str())
+                .map(|coin| coin.amount.parse::<u128>())
+                .sum::<std::result::Result<u128, _>>()?;
+            (fee_amount <= max_fee).then_some(()).context(format!(
+                "fee {fee_amount}{fee_denom} is above the max fee {max_fee}{fee_denom}"
+            ))
+        };
 
-                            let source_acc = accounts.get(source_key).expect("not exists");
-                            let target_acc = accounts.get(target_key).expect("not exists");
+        // txs of this process are not in the queried sequence until the next block
+        let sequence_tracker = SequenceTracker::global();
+        let generate_unsigned_transaction = |any_msgs: Vec<Any>| {
+            let (endpoints, tx_options) = (&endpoints, &tx_options);
+            let (signer_address, fee_granter) = (&signer_address, &fee_granter);
+            async move {
+                let any_msgs = match executor {
+                    Some(_) => vec![Any::try_pack(generate_grant_exec(
+                        signer_address,
+                        &any_msgs,
+                    )?)?],
+                    None => any_msgs,
+                };
+                let (account_number, unsigned_tx) = futures::stream::iter(endpoints.rpc.iter())
+                    .then(|rpc_endpoint| {
+                        signer.generate_unsigned_transaction(
+                            signer_address,
+                            &any_msgs,
+                            fee,
+                            fee_granter,
+                            rpc_endpoint,
+                            tx_options,
+                        )
+                    })
+                    .filter_map(|x| async { x.ok() })
+                    .boxed_local()
+                    .next()
+                    .await
+                    .context("not able to create tx")?;
+                let sequence =
+                    sequence_tracker.next(chain_id, signer_address, get_sequence(&unsigned_tx)?);
+                Result::Ok((account_number, set_sequence(unsigned_tx, sequence)?))
+            }
+        };
 
-                            let source = source_acc.address(hrp)?;
-                            let target = target_acc.address(hrp)?;
+        let chunks = match self {
+            Self::Batch {
+                split: Some(max_gas),
+                ..
+            } => {
+                split_by_gas(
+                    &endpoints,
+                    any_msgs,
+                    *max_gas,
+                    &generate_unsigned_transaction,
+                )
+                .await?
+            }
+            Self::Payout {
+                chunk_size: Some(chunk_size),
+                ..
+            } => any_msgs.chunks(*chunk_size).map(<[Any]>::to_vec).collect(),
+            _ => vec![any_msgs],
+        };
 
-                            let local_transfer =
-                                crate::msg::local_token_transfer(&source, &target, *amount, denom)?;
+        for (index, any_msgs) in chunks.iter().enumerate() {
+            let (account_number, unsigned_tx) =
+                generate_unsigned_transaction(any_msgs.clone()).await?;
+
+            let unsigned_tx = match gas_price {
+                Some(_) => update_tx_with_gas(unsigned_tx, tx_options.gas_limit)?,
+                None => unsigned_tx,
+            };
+
+            println!("{}", serde_json::to_string_pretty(&unsigned_tx)?);
+
+            // simulated without signing, so a Ledger is asked only once
+            let needed_gas = match options.gas {
+                Gas::Auto => estimate_gas(&endpoints, &unsigned_tx).await,
+                Gas::Limit(_) => None,
+            };
+
+            // multisig members sign it separately, see `multisig-sign` and `multisig-combine`
+            if output.generate_only || signer.is_multisig() {
+                if matches!(options.gas, Gas::Auto) && needed_gas.is_none() {
+                    println!(
+                        "warning: not able to simulate, keeping the gas limit of {}",
+                        tx_options.gas_limit
+                    );
+                }
+                let unsigned_tx = match needed_gas {
+                    Some(needed_gas) => {
+                        update_tx_with_gas(unsigned_tx, options.adjusted_gas(needed_gas))?
+                    }
+                    None => unsigned_tx,
+                };
+                check_max_fee(&unsigned_tx)?;
+                // generated txs are not broadcast, so the later chunks take the next sequences
+                let sequence = get_sequence(&unsigned_tx)? + index as u64;
+                let unsigned_tx = set_sequence(unsigned_tx, sequence)?;
+                output.write(
+                    &TxFile::new(chain_id, account_number, unsigned_tx)?,
+                    (chunks.len() > 1).then_some(index),
+                )?;
+                continue;
+            }
+
+            let unsigned_tx = match options.gas {
+                Gas::Auto => {
+                    let needed_gas = needed_gas.context("not able to simulate tx")?;
+                    update_tx_with_gas(unsigned_tx, options.adjusted_gas(needed_gas))?
+                }
+                Gas::Limit(_) => unsigned_tx,
+            };
 
-                            any_msgs.push(Any::try_pack(local_transfer)?);
+            check_max_fee(&unsigned_tx)?;
 
-                            (source_acc, any_msgs)
-                        }
-                        Self::Restake {
-                            account: account_key,
-                        } => {
-                            let account_acc = accounts.get(account_key).expect("not exists");
-                            let account = account_acc.address(hrp)?;
-
-                            let delegations =
-                                crate::query::get_delegated(&account, rpc_endpoint).await?;
-                            let rewards = crate::query::get_rewards(&account, rpc_endpoint).await?;
-
-                            let validator = delegations
-                                .first()
-                                .context("delegator should have atleast one validator")?
-                                .0
-                                .clone();
-
-                            let total_delegations =
-                                delegations.into_iter().map(|x| x.1).sum::<u128>();
-
-                            let total_rewards = rewards
-                                .into_iter()
-                                .find(|x| x.0 == denom)
-                                .map(|x| x.1)
-                                .unwrap_or(0);
-
-                            let mut any_msgs = vec![];
-
-                            if (total_rewards * total_rewards) > (fee.0 * total_delegations) {
-                                let withdraw_msgs =
-                                    crate::msg::claim_all_reward(&account, rpc_endpoint).await?;
-
-                                any_msgs.extend(
-                                    withdraw_msgs
-                                        .into_iter()
-                                        .map(|x| Ok(Any::try_pack(x)?))
-                                        .collect::<Result<Vec<_>>>()?,
-                                );
-                                const BUFFER_BALANCE_AMOUNT: u128 = 10_000;
-
-                                if total_rewards > BUFFER_BALANCE_AMOUNT {
-                                    let delegate_msg = crate::msg::delegate_to(
-                                        total_rewards,
-                                        denom,
-                                        &validator,
-                                        &account,
-                                    );
-
-                                    any_msgs.push(Any::try_pack(delegate_msg)?);
-                                }
-                            }
-                            (account_acc, any_msgs)
-                        }
-                        Self::Delegate {
-                            account: account_key,
-                            validator: validator_opt,
-                            amount,
-                        } => {
-                            let account_acc = accounts.get(account_key).expect("not exists");
-                            let account = account_acc.address(hrp)?;
-                            let mut any_msgs = vec![];
-
-                            let validator = match validator_opt {
-                                Some(validator) => validator.into(),
-                                None => {
-                                    let delegations =
-                                        crate::query::get_delegated(&account, rpc_endpoint).await?;
-
-                                    delegations
-                                        .first()
-                                        .context("delegator should have atleast one validator")?
-                                        .0
-                                        .clone()
-                                }
-                            };
-
-                            let delegate_msg = MsgDelegate {
-                                delegator_address: account,
-                                validator_address: validator,
-                                amount: Some(Coin {
-                                    denom: denom.into(),
-                                    amount: amount.to_string(),
-                                }),
-                            };
-
-                            any_msgs.push(Any::try_pack(delegate_msg)?);
-
-                            (account_acc, any_msgs)
-                        }
-                        Self::Redelegate {
-                            account: account_key,
-                            source,
-                            target,
-                            amount,
-                        } => {
-                            let account_acc = accounts.get(account_key).expect("not exists");
-                            let account = account_acc.address(hrp)?;
-                            let mut any_msgs = vec![];
-
-                            let final_amount = match amount {
-                                Some(value) => *value,
-                                None => {
-                                    let m: HashMap<_, _> =
-                                        crate::query::get_delegated(&account, rpc_endpoint)
-                                            .await?
-                                            .into_iter()
-                                            .collect();
-                                    m[source]
-                                }
-                            };
-
-                            let redelegate_msg = MsgBeginRedelegate {
-                                delegator_address: account,
-                                validator_src_address: source.into(),
-                                validator_dst_address: target.into(),
-                                amount: Some(Coin {
-                                    denom: denom.into(),
-                                    amount: final_amount.to_string(),
-                                }),
-                            };
-
-                            any_msgs.push(Any::try_pack(redelegate_msg)?);
-
-                            (account_acc, any_msgs)
-                        }
-                        Self::RestakeApp {
-                            granter: granter_key,
-                            grantee,
-                            validator,
-                        } => {
-                            let granter_acc = accounts.get(granter_key).expect("not exists");
-                            let granter = granter_acc.address(hrp)?;
-
-                            let restake_app_msg =
-                                crate::msg::restake_app_auth(&granter, grantee, validator)?;
-
-                            (granter_acc, vec![Any::try_pack(restake_app_msg)?])
-                        }
-                        Self::RestakeAppRevoke {
-                            granter: granter_key,
-                            grantee,
-                        } => {
-                            let granter_acc = accounts.get(granter_key).expect("not exists");
-                            let granter = granter_acc.address(hrp)?;
+            if broadcast.dry_run {
+                let signed_tx = signer
+                    .sign_unsigned_transaction(&unsigned_tx, chain_id, account_number)
+                    .await?;
 
-                            let restake_app_msg =
-                                crate::msg::restake_app_auth_revoke(&granter, grantee)?;
+                println!("{}", serde_json::to_string_pretty(&signed_tx)?);
 
-                            (granter_acc, vec![Any::try_pack(restake_app_msg)?])
-                        }
-                        Self::Grant {
-                            granter: granter_key,
-                            grantee: grantee_key,
-                        } => {
-                            let granter_acc = accounts.get(granter_key).expect("not exists");
-                            let grantee_acc = accounts.get(grantee_key).expect("not exists");
+                continue;
+            }
 
-                            let granter = granter_acc.address(hrp)?;
-                            let grantee = grantee_acc.address(hrp)?;
+            let (signed_tx, resp) = signer
+                .sign_and_broadcast(
+                    &unsigned_tx,
+                    chain_id,
+                    account_number,
+                    &signer_address,
+                    &endpoints,
+                    sequence_tracker,
+                )
+                .await?;
 
-                            let mut any_msgs = vec![];
+            println!("{}", serde_json::to_string_pretty(&signed_tx)?);
 
-                            let authz_msgs = crate::msg::generate_usual_auth(&granter, &grantee)?;
+            let tx_result = wait_for_inclusion(&endpoints, &resp, broadcast).await?;
 
-                            any_msgs.extend(
-                                authz_msgs
-                                    .into_iter()
-                                    .map(|x| Ok(Any::try_pack(x)?))
-                                    .collect::<Result<Vec<_>>>()?,
-                            );
+            if let Self::IBCTransfer {
+                track: Some(track), ..
+            } = self
+            {
+                let tx_result = tx_result.context("--track needs a --wait-timeout above zero")?;
 
-                            let fee_allowance =
-                                crate::msg::generate_feeallowance(&granter, &grantee)?;
+                for packet in SendPacket::from_tx_result(&tx_result)? {
+                    let destination_chain_id = futures::stream::iter(&endpoints.rpc)
+                        .then(|rpc_endpoint| {
+                            get_channel_client_state(
+                                rpc_endpoint,
+                                &packet.src_port,
+                                &packet.src_channel,
+                            )
+                        })
+                        .filter_map(|x| async { x.ok() })
+                        .boxed_local()
+                        .next()
+                        .await
+                        .context("not able to find the destination chain")?
+                        .chain_id;
+
+                    let destination = get_rpc_endpoints(&destination_chain_id, graphql_endpoint)
+                        .await?
+                        .into_iter()
+                        .map(|(_, rpc_endpoint)| rpc_endpoint)
+                        .collect::<Vec<_>>();
+
+                    track_packet(
+                        &endpoints.rpc,
+                        &destination,
+                        &packet,
+                        std::time::Duration::from_secs(*track),
+                    )
+                    .await?;
+                }
+            }
+        }
 
-                            any_msgs.push(Any::try_pack(fee_allowance)?);
+        Ok(())
+    }
 
-                            if get_account_number_and_sequence(rpc_endpoint, &grantee)
-                                .await
-                                .is_err()
-                            {
-                                let unit = crate::msg::unit_transfer(&granter, &grantee, denom)?;
-                                any_msgs.push(Any::try_pack(unit)?);
-                            }
+    /// Messages of the transaction, with the account which signs them. With
+    /// `authz`, they are executed by a grantee, so votes stay v1beta1, which is
+    /// the vote of the usual grants.
+    async fn any_msgs<'a>(
+        &self,
+        accounts: &'a HashMap<String, Account>,
+        contacts: &HashMap<String, String>,
+        chain: &crate::chain::Chain,
+        fee_amount: u128,
+        rpc_endpoint: &str,
+        authz: bool,
+    ) -> Result<(&'a Account, Vec<Any>)> {
+        let hrp = chain.prefix.as_str();
+        let denom = chain.denom.as_str();
 
-                            (granter_acc, any_msgs)
+        Ok(match self {
+            Self::Send {
+                source: source_key,
+                target: target_key,
+                amount,
+            } => {
+                let mut any_msgs = vec![];
+
+                let source_acc = accounts
+                    .get(source_key)
+                    .context(format!("{source_key} is not in accounts"))?;
+
+                let source = source_acc.address(hrp)?;
+                let target = resolve_address(target_key, accounts, contacts, hrp)?;
+
+                let local_transfer =
+                    crate::msg::local_token_transfer(&source, &target, *amount, denom)?;
+
+                any_msgs.push(Any::try_pack(local_transfer)?);
+
+                (source_acc, any_msgs)
+            }
+            Self::Restake {
+                account: account_key,
+            } => {
+                let account_acc = accounts
+                    .get(account_key)
+                    .context(format!("{account_key} is not in accounts"))?;
+                let account = account_acc.address(hrp)?;
+
+                let delegations = crate::query::get_delegated(&account, rpc_endpoint).await?;
+                let rewards = crate::query::get_rewards(&account, rpc_endpoint).await?;
+
+                let validator = delegations
+                    .first()
+                    .context("delegator should have atleast one validator")?
+                    .0
+                    .clone();
+
+                let total_delegations = delegations.into_iter().map(|x| x.1).sum::<u128>();
+
+                let total_rewards = rewards
+                    .into_iter()
+                    .find(|x| x.0 == denom)
+                    .map(|x| x.1)
+                    .unwrap_or(0);
+
+                let mut any_msgs = vec![];
+
+                if (total_rewards * total_rewards) > (fee_amount * total_delegations) {
+                    let withdraw_msgs =
+                        crate::msg::claim_all_reward(&account, rpc_endpoint).await?;
+
+                    any_msgs.extend(
+                        withdraw_msgs
+                            .into_iter()
+                            .map(|x| Ok(Any::try_pack(x)?))
+                            .collect::<Result<Vec<_>>>()?,
+                    );
+                    const BUFFER_BALANCE_AMOUNT: u128 = 10_000;
+
+                    if total_rewards > BUFFER_BALANCE_AMOUNT {
+                        let delegate_msg =
+                            crate::msg::delegate_to(total_rewards, denom, &validator, &account);
+
+                        any_msgs.push(Any::try_pack(delegate_msg)?);
+                    }
+                }
+                (account_acc, any_msgs)
+            }
+            Self::Delegate {
+                account: account_key,
+                validator: validator_opt,
+                amount,
+            } => {
+                let account_acc = accounts
+                    .get(account_key)
+                    .context(format!("{account_key} is not in accounts"))?;
+                let account = account_acc.address(hrp)?;
+                let mut any_msgs = vec![];
+
+                let validator = match validator_opt {
+                    Some(validator) => validator.into(),
+                    None => {
+                        let delegations =
+                            crate::query::get_delegated(&account, rpc_endpoint).await?;
+
+                        delegations
+                            .first()
+                            .context("delegator should have atleast one validator")?
+                            .0
+                            .clone()
+                    }
+                };
+
+                let delegate_msg = MsgDelegate {
+                    delegator_address: account,
+                    validator_address: validator,
+                    amount: Some(Coin {
+                        denom: denom.into(),
+                        amount: amount.to_string(),
+                    }),
+                };
+
+                any_msgs.push(Any::try_pack(delegate_msg)?);
+
+                (account_acc, any_msgs)
+            }
+            Self::Redelegate {
+                account: account_key,
+                source,
+                target,
+                amount,
+            } => {
+                let account_acc = accounts
+                    .get(account_key)
+                    .context(format!("{account_key} is not in accounts"))?;
+                let account = account_acc.address(hrp)?;
+                let mut any_msgs = vec![];
+
+                let final_amount = match amount {
+                    Some(value) => *value,
+                    None => {
+                        let m: HashMap<_, _> = crate::query::get_delegated(&account, rpc_endpoint)
+                            .await?
+                            .into_iter()
+                            .collect();
+                        m[source]
+                    }
+                };
+
+                let redelegate_msg = MsgBeginRedelegate {
+                    delegator_address: account,
+                    validator_src_address: source.into(),
+                    validator_dst_address: target.into(),
+                    amount: Some(Coin {
+                        denom: denom.into(),
+                        amount: final_amount.to_string(),
+                    }),
+                };
+
+                any_msgs.push(Any::try_pack(redelegate_msg)?);
+
+                (account_acc, any_msgs)
+            }
+            Self::Undelegate {
+                account: account_key,
+                validator,
+                amount,
+            } => {
+                let account_acc = accounts
+                    .get(account_key)
+                    .context(format!("{account_key} is not in accounts"))?;
+                let account = account_acc.address(hrp)?;
+
+                let final_amount = match amount {
+                    Some(value) => *value,
+                    None => {
+                        let m: HashMap<_, _> = crate::query::get_delegated(&account, rpc_endpoint)
+                            .await?
+                            .into_iter()
+                            .collect();
+                        *m.get(validator).context("no delegation to the validator")?
+                    }
+                };
+
+                let undelegate_msg = MsgUndelegate {
+                    delegator_address: account,
+                    validator_address: validator.into(),
+                    amount: Some(Coin {
+                        denom: denom.into(),
+                        amount: final_amount.to_string(),
+                    }),
+                };
+
+                (account_acc, vec![Any::try_pack(undelegate_msg)?])
+            }
+            Self::CancelUnbonding {
+                account: account_key,
+                validator,
+                amount,
+                creation_height,
+            } => {
+                let account_acc = accounts
+                    .get(account_key)
+                    .context(format!("{account_key} is not in accounts"))?;
+                let account = account_acc.address(hrp)?;
+
+                let (creation_height, final_amount) = match (creation_height, amount) {
+                    (Some(creation_height), Some(value)) => (*creation_height, *value),
+                    _ => {
+                        let entries =
+                            crate::query::get_unbonding(&account, validator, rpc_endpoint).await?;
+                        let entry = match creation_height {
+                            Some(creation_height) => entries
+                                .into_iter()
+                                .find(|(height, _)| height == creation_height),
+                            None => entries.into_iter().max_by_key(|(height, _)| *height),
                         }
-                        Self::Revoke {
-                            granter: granter_key,
-                            grantee: grantee_key,
-                        } => {
-                            let granter_acc = accounts.get(granter_key).expect("not exists");
-                            let grantee_acc = accounts.get(grantee_key).expect("not exists");
-
-                            let granter = granter_acc.address(hrp)?;
-                            let grantee = grantee_acc.address(hrp)?;
+                        .context("no such unbonding entry")?;
+                        (entry.0, amount.unwrap_or(entry.1))
+                    }
+                };
+
+                let cancel_unbonding_msg = MsgCancelUnbondingDelegation {
+                    delegator_address: account,
+                    validator_address: validator.into(),
+                    amount: Some(Coin {
+                        denom: denom.into(),
+                        amount: final_amount.to_string(),
+                    }),
+                    creation_height,
+                };
+
+                (account_acc, vec![Any::try_pack(cancel_unbonding_msg)?])
+            }
+            Self::SetWithdrawAddress {
+                account: account_key,
+                withdraw_address,
+            } => {
+                let account_acc = accounts
+                    .get(account_key)
+                    .context(format!("{account_key} is not in accounts"))?;
+                let account = account_acc.address(hrp)?;
+
+                let set_withdraw_address_msg = MsgSetWithdrawAddress {
+                    delegator_address: account,
+                    withdraw_address: resolve_address(withdraw_address, accounts, contacts, hrp)?,
+                };
+
+                (account_acc, vec![Any::try_pack(set_withdraw_address_msg)?])
+            }
+            Self::WithdrawCommission {
+                account: account_key,
+            } => {
+                let account_acc = accounts
+                    .get(account_key)
+                    .context(format!("{account_key} is not in accounts"))?;
+                let valoper = bech32(&account_acc.address(hrp)?, &chain.valoper_prefix())?;
+
+                let withdraw_commission_msg = MsgWithdrawValidatorCommission {
+                    validator_address: valoper,
+                };
+
+                (account_acc, vec![Any::try_pack(withdraw_commission_msg)?])
+            }
+            Self::Unjail {
+                account: account_key,
+            } => {
+                let account_acc = accounts
+                    .get(account_key)
+                    .context(format!("{account_key} is not in accounts"))?;
+                let valoper = bech32(&account_acc.address(hrp)?, &chain.valoper_prefix())?;
+
+                let unjail_msg = MsgUnjail {
+                    validator_addr: valoper,
+                };
+
+                (account_acc, vec![Any::try_pack(unjail_msg)?])
+            }
+            Self::CreateValidator {
+                account: account_key,
+                amount,
+                priv_validator_key,
+                description,
+                commission_rate,
+                commission_max_rate,
+                commission_max_change_rate,
+                min_self_delegation,
+            } => {
+                let account_acc = accounts
+                    .get(account_key)
+                    .context(format!("{account_key} is not in accounts"))?;
+                let account = account_acc.address(hrp)?;
+                let valoper = bech32(&account, &chain.valoper_prefix())?;
+
+                description
+                    .moniker
+                    .is_some()
+                    .then_some(())
+                    .context("a validator needs a --moniker")?;
+
+                // commission rates are `LegacyDec`s, which are encoded as their integers
+                let create_validator_msg = MsgCreateValidator {
+                    description: Some(description.to_proto("")),
+                    commission: Some(CommissionRates {
+                        rate: parse_legacy_dec(commission_rate)?.to_string(),
+                        max_rate: parse_legacy_dec(commission_max_rate)?.to_string(),
+                        max_change_rate: parse_legacy_dec(commission_max_change_rate)?.to_string(),
+                    }),
+                    min_self_delegation: min_self_delegation.clone(),
+                    delegator_address: account,
+                    validator_address: valoper,
+                    pubkey: Some(read_consensus_public_key(priv_validator_key)?),
+                    value: Some(Coin {
+                        denom: denom.into(),
+                        amount: amount.to_string(),
+                    }),
+                };
+
+                (account_acc, vec![Any::try_pack(create_validator_msg)?])
+            }
+            Self::EditValidator {
+                account: account_key,
+                description,
+                commission_rate,
+                min_self_delegation,
+            } => {
+                let account_acc = accounts
+                    .get(account_key)
+                    .context(format!("{account_key} is not in accounts"))?;
+                let valoper = bech32(&account_acc.address(hrp)?, &chain.valoper_prefix())?;
+
+                // empty rates and amounts, and `[do-not-modify]` fields are kept
+                let edit_validator_msg = MsgEditValidator {
+                    description: Some(description.to_proto("[do-not-modify]")),
+                    validator_address: valoper,
+                    commission_rate: match commission_rate {
+                        Some(commission_rate) => parse_legacy_dec(commission_rate)?.to_string(),
+                        None => String::new(),
+                    },
+                    min_self_delegation: min_self_delegation.clone().unwrap_or_default(),
+                };
+
+                (account_acc, vec![Any::try_pack(edit_validator_msg)?])
+            }
+            Self::RestakeApp {
+                granter: granter_key,
+                grantee,
+                validator,
+            } => {
+                let granter_acc = accounts
+                    .get(granter_key)
+                    .context(format!("{granter_key} is not in accounts"))?;
+                let granter = granter_acc.address(hrp)?;
+
+                let grantee = resolve_address(grantee, accounts, contacts, hrp)?;
+
+                let restake_app_msg = crate::msg::restake_app_auth(&granter, &grantee, validator)?;
+
+                (granter_acc, vec![Any::try_pack(restake_app_msg)?])
+            }
+            Self::RestakeAppRevoke {
+                granter: granter_key,
+                grantee,
+            } => {
+                let granter_acc = accounts
+                    .get(granter_key)
+                    .context(format!("{granter_key} is not in accounts"))?;
+                let granter = granter_acc.address(hrp)?;
+
+                let grantee = resolve_address(grantee, accounts, contacts, hrp)?;
+
+                let restake_app_msg = crate::msg::restake_app_auth_revoke(&granter, &grantee)?;
+
+                (granter_acc, vec![Any::try_pack(restake_app_msg)?])
+            }
+            Self::Grant {
+                granter: granter_key,
+                grantee: grantee_key,
+                operator,
+            } => {
+                let granter_acc = accounts
+                    .get(granter_key)
+                    .context(format!("{granter_key} is not in accounts"))?;
+
+                let granter = granter_acc.address(hrp)?;
+                let grantee = resolve_address(grantee_key, accounts, contacts, hrp)?;
+
+                let mut any_msgs = vec![];
+
+                let mut authz_msgs = crate::msg::generate_usual_auth(&granter, &grantee)?;
+
+                if *operator {
+                    authz_msgs.extend(crate::msg::generate_operator_auth(&granter, &grantee)?);
+                }
 
-                            let mut any_msgs = vec![];
+                any_msgs.extend(
+                    authz_msgs
+                        .into_iter()
+                        .map(|x| Ok(Any::try_pack(x)?))
+                        .collect::<Result<Vec<_>>>()?,
+                );
 
-                            let revoke_msgs =
-                                crate::msg::generate_usual_revoke(&granter, &grantee)?;
+                let fee_allowance = crate::msg::generate_feeallowance(&granter, &grantee)?;
 
-                            any_msgs.extend(
-                                revoke_msgs
-                                    .into_iter()
-                                    .map(|x| Ok(Any::try_pack(x)?))
-                                    .collect::<Result<Vec<_>>>()?,
-                            );
+                any_msgs.push(Any::try_pack(fee_allowance)?);
 
-                            let fee_revoke_allowance =
-                                crate::msg::generate_revoke_feeallowance(&granter, &grantee)?;
+                if get_account_number_and_sequence(rpc_endpoint, &grantee)
+                    .await
+                    .is_err()
+                {
+                    let unit = crate::msg::unit_transfer(&granter, &grantee, denom)?;
+                    any_msgs.push(Any::try_pack(unit)?);
+                }
 
-                            any_msgs.push(Any::try_pack(fee_revoke_allowance)?);
+                (granter_acc, any_msgs)
+            }
+            Self::Revoke {
+                granter: granter_key,
+                grantee: grantee_key,
+                operator,
+            } => {
+                let granter_acc = accounts
+                    .get(granter_key)
+                    .context(format!("{granter_key} is not in accounts"))?;
 
-                            (granter_acc, any_msgs)
-                        }
-                        Self::Vote { voter, votes } => (
-                            accounts.get(voter).expect("not exists"),
-                            votes
-                                .iter()
-                                .map(|vote| {
-                                    Ok(Any::try_pack(MsgVote {
-                                        proposal_id: vote.proposal_id,
-                                        voter: accounts
-                                            .get(voter)
-                                            .context("voter is not in accounts")?
-                                            .address(hrp)?,
-                                        option: vote.option.into(),
-                                    })?)
-                                })
-                                .collect::<Result<Vec<_>>>()?,
-                        ),
-                        Self::IBCTransfer {
-                            source_channel,
-                            token,
-                            sender,
-                            receiver_address,
-                        } => {
-                            let account_acc = accounts.get(sender).expect("not exists");
-                            let account = account_acc.address(hrp)?;
-
-                            let ibc_transfer = MsgTransfer {
-                                source_port: "transfer".into(),
-                                source_channel: source_channel.into(),
-                                token: Some(token.clone()),
-                                sender: account,
-                                receiver: receiver_address.into(),
-                                timeout_height: None,
-                                timeout_timestamp: (chrono::Utc::now()
-                                    + chrono::Duration::minutes(10))
-                                .timestamp_nanos_opt()
-                                .context("error while converting time")?
-                                    as u64,
-                            };
-                            (account_acc, vec![Any::try_pack(ibc_transfer)?])
-                        }
-                        Self::Cosmwasm {
-                            json,
-                            funds,
-                            sender,
-                            contract_address,
-                        } => {
-                            let account_acc = accounts.get(sender).expect("not exists");
-                            let account = account_acc.address(hrp)?;
-
-                            let cw_execute = MsgExecuteContract {
-                                sender: account,
-                                contract: contract_address.into(),
-                                msg: json.as_bytes().to_vec(),
-                                funds: vec![funds.clone()],
-                            };
-
-                            (account_acc, vec![Any::try_pack(cw_execute)?])
-                        }
-                    };
+                let granter = granter_acc.address(hrp)?;
+                let grantee = resolve_address(grantee_key, accounts, contacts, hrp)?;
 
-                    if let Some(grantee) = executor {
-                        fee_granter = owner.address(hrp)?;
-                        owner = accounts.get(grantee).expect("not exists");
-                        any_msgs = vec![Any::try_pack(generate_grant_exec(
-                            &owner.address(hrp)?,
-                            &any_msgs,
-                        )?)?];
-                    }
+                let mut any_msgs = vec![];
 
-                    let (acc_number, tx) = owner
-                        .generate_unsigned_transaction(
-                            &owner.address(hrp)?,
-                            &any_msgs,
-                            fee,
-                            &fee_granter,
-                            rpc_endpoint,
-                        )
-                        .await?;
+                let mut revoke_msgs = crate::msg::generate_usual_revoke(&granter, &grantee)?;
 
-                    Result::Ok((owner.clone(), acc_number, tx))
+                if *operator {
+                    revoke_msgs.extend(crate::msg::generate_operator_revoke(&granter, &grantee)?);
                 }
-            })
-            .filter_map(|x| async { x.ok() })
-            .boxed_local()
-            .next()
-            .await
-            .expect("not able to create tx");
 
-        println!("{}", serde_json::to_string_pretty(&unsigned_tx)?);
+                any_msgs.extend(
+                    revoke_msgs
+                        .into_iter()
+                        .map(|x| Ok(Any::try_pack(x)?))
+                        .collect::<Result<Vec<_>>>()?,
+                );
+
+                let fee_revoke_allowance =
+                    crate::msg::generate_revoke_feeallowance(&granter, &grantee)?;
+
+                any_msgs.push(Any::try_pack(fee_revoke_allowance)?);
+
+                (granter_acc, any_msgs)
+            }
+            Self::Vote { voter, votes } => {
+                let voter_acc = accounts.get(voter).context("voter is not in accounts")?;
+                let voter = voter_acc.address(hrp)?;
+                let vote_v1 = !authz && supports_gov_v1(rpc_endpoint).await?;
+
+                let any_msgs = votes
+                    .iter()
+                    .map(|vote| {
+                        crate::msg::generate_vote(vote_v1, vote.proposal_id, &voter, &vote.vote.0)
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                (voter_acc, any_msgs)
+            }
+            Self::VoteAll {
+                voter,
+                default,
+                votes,
+            } => {
+                let voter_acc = accounts.get(voter).context("voter is not in accounts")?;
+                let voter = voter_acc.address(hrp)?;
+                let gov_v1 = supports_gov_v1(rpc_endpoint).await?;
+
+                let mut any_msgs = vec![];
+
+                for proposal_id in get_voting_proposals(rpc_endpoint, gov_v1).await? {
+                    let vote = votes
+                        .iter()
+                        .find(|vote| vote.proposal_id == proposal_id)
+                        .map(|vote| &vote.vote)
+                        .or(default.as_ref());
+
+                    let Some(vote) = vote else {
+                        println!("no vote for proposal {proposal_id}, skipping");
+                        continue;
+                    };
 
-        let signed_tx = futures::stream::iter(rpc_endpoints.iter())
-            .then(|(_, rpc_endpoint)| {
-                let unsigned_tx = unsigned_tx.clone();
-                let owner = owner.clone();
-                async move {
-                    println!("add gas and trying with {}", &rpc_endpoint);
+                    if has_voted(rpc_endpoint, gov_v1, proposal_id, &voter).await? {
+                        println!("already voted on proposal {proposal_id}, skipping");
+                        continue;
+                    }
 
-                    let signed_but_needed_gas_tx = owner
-                        .sign_unsigned_transaction(&unsigned_tx, chain_id, account_number)
-                        .await?;
+                    any_msgs.push(crate::msg::generate_vote(
+                        gov_v1 && !authz,
+                        proposal_id,
+                        &voter,
+                        &vote.0,
+                    )?);
+                }
 
-                    let needed_gas = crate::broadcast::simulate_via_tendermint_rpc(
+                (!any_msgs.is_empty())
+                    .then_some(())
+                    .context("no proposals to vote on")?;
+
+                (voter_acc, any_msgs)
+            }
+            Self::Deposit {
+                depositor,
+                proposal_id,
+                amount,
+            } => {
+                let depositor_acc = accounts
+                    .get(depositor)
+                    .context(format!("{depositor} is not in accounts"))?;
+                let depositor = depositor_acc.address(hrp)?;
+
+                let deposit_msg = if supports_gov_v1(rpc_endpoint).await? {
+                    Any::try_pack(v1::MsgDeposit {
+                        proposal_id: *proposal_id,
+                        depositor,
+                        amount: vec![amount.clone()],
+                    })?
+                } else {
+                    Any::try_pack(v1beta1::MsgDeposit {
+                        proposal_id: *proposal_id,
+                        depositor,
+                        amount: vec![amount.clone()],
+                    })?
+                };
+
+                (depositor_acc, vec![deposit_msg])
+            }
+            Self::SubmitProposal {
+                proposer,
+                title,
+                summary,
+                metadata,
+                messages,
+                deposit,
+            } => {
+                let proposer_acc = accounts
+                    .get(proposer)
+                    .context(format!("{proposer} is not in accounts"))?;
+                let proposer = proposer_acc.address(hrp)?;
+
+                let messages = match messages {
+                    Some(messages) => crate::msg::any_msgs_from_json(messages)?,
+                    None => vec![],
+                };
+
+                let proposal_msg = if supports_gov_v1(rpc_endpoint).await? {
+                    Any::try_pack(v1::MsgSubmitProposal {
+                        messages,
+                        initial_deposit: deposit.iter().cloned().collect(),
+                        proposer,
+                        metadata: metadata.clone(),
+                        title: title.clone(),
+                        summary: summary.clone(),
+                        ..Default::default()
+                    })?
+                } else {
+                    // v1beta1 only has text proposals, without messages
+                    messages
+                        .is_empty()
+                        .then_some(())
+                        .context("proposals with messages need gov v1")?;
+                    Any::try_pack(v1beta1::MsgSubmitProposal {
+                        content: Some(Any::try_pack(v1beta1::TextProposal {
+                            title: title.clone(),
+                            description: summary.clone(),
+                        })?),
+                        initial_deposit: deposit.iter().cloned().collect(),
+                        proposer,
+                    })?
+                };
+
+                (proposer_acc, vec![proposal_msg])
+            }
+            Self::IBCTransfer {
+                source_channel,
+                to_chain,
+                token,
+                sender,
+                receiver_address,
+                memo,
+                timeout_height,
+                hops,
+                ..
+            } => {
+                let account_acc = accounts
+                    .get(sender)
+                    .context(format!("{sender} is not in accounts"))?;
+                let account = account_acc.address(hrp)?;
+
+                let project_dir = directories::ProjectDirs::from("systems", "rnbguy", "rover")
+                    .context("project dir")?;
+                let chains_path = project_dir.data_local_dir().join("chains.yaml");
+                let chains_path_str = chains_path.to_str().context("project path")?;
+
+                let chains: HashMap<String, crate::chain::Chain> =
+                    read_data_from_yaml(chains_path_str)?;
+
+                let to_chain = to_chain
+                    .as_ref()
+                    .map(|chain_id| {
+                        chains
+                            .get(chain_id)
+                            .context(format!("{chain_id} is not in chains.yaml"))
+                    })
+                    .transpose()?;
+
+                let source_channel = match source_channel {
+                    Some(source_channel) => source_channel.clone(),
+                    None => {
+                        let to_chain =
+                            to_chain.context("no source channel or destination chain")?;
+                        find_transfer_channel(rpc_endpoint, &to_chain.chain_id).await?
+                    }
+                };
+                let source_channel = source_channel.as_str();
+
+                let token = Coin {
+                    denom: resolve_ibc_denom(
                         rpc_endpoint,
-                        signed_but_needed_gas_tx.clone(),
+                        &token.denom,
+                        &chain.denom,
+                        source_channel,
                     )
+                    .await?,
+                    amount: token.amount.clone(),
+                };
+
+                // the receiver is on the destination chain, unless it is forwarded further
+                let receiver = match to_chain {
+                    Some(to_chain) if hops.is_empty() => {
+                        resolve_address(receiver_address, accounts, contacts, &to_chain.prefix)?
+                    }
+                    _ => contacts
+                        .get(receiver_address)
+                        .unwrap_or(receiver_address)
+                        .clone(),
+                };
+
+                let (receiver, memo) = if hops.is_empty() {
+                    (receiver, memo.clone().unwrap_or_default())
+                } else {
+                    // the sender receives on the intermediate chains
+                    let mut receivers = hops
+                        .iter()
+                        .map(|hop| {
+                            account_acc.address(
+                                &chains
+                                    .get(&hop.chain_id)
+                                    .context(format!("{} is not in chains.yaml", hop.chain_id))?
+                                    .prefix,
+                            )
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    receivers.push(receiver);
+
+                    let next = memo
+                        .as_deref()
+                        .map(serde_json::from_str)
+                        .transpose()
+                        .context("memo must be JSON to forward it")?;
+
+                    let forwards = receivers[1..]
+                        .iter()
+                        .cloned()
+                        .zip(hops.iter().map(|hop| hop.channel.clone()))
+                        .collect::<Vec<_>>();
+
+                    (
+                        receivers[0].clone(),
+                        forward_memo(&forwards, next)
+                            .context("no forward memo")?
+                            .to_string(),
+                    )
+                };
+
+                let (timeout_height, timeout_timestamp) = match timeout_height {
+                    Some(blocks) => {
+                        let height =
+                            get_counterparty_height(rpc_endpoint, "transfer", source_channel)
+                                .await?;
+                        (
+                            Some(Height {
+                                revision_number: height.revision_number,
+                                revision_height: height.revision_height + blocks,
+                            }),
+                            0,
+                        )
+                    }
+                    None => (
+                        None,
+                        (chrono::Utc::now() + chrono::Duration::minutes(10))
+                            .timestamp_nanos_opt()
+                            .context("error while converting time")? as u64,
+                    ),
+                };
+
+                (
+                    account_acc,
+                    vec![ibc_transfer(
+                        source_channel,
+                        token,
+                        &account,
+                        &receiver,
+                        timeout_height,
+                        timeout_timestamp,
+                        &memo,
+                    )],
+                )
+            }
+            Self::Cosmwasm {
+                json,
+                funds,
+                sender,
+                contract_address,
+            } => {
+                let account_acc = accounts
+                    .get(sender)
+                    .context(format!("{sender} is not in accounts"))?;
+                let account = account_acc.address(hrp)?;
+
+                let cw_execute = MsgExecuteContract {
+                    sender: account,
+                    contract: contract_address.into(),
+                    msg: json.as_bytes().to_vec(),
+                    funds: vec![funds.clone()],
+                };
+
+                (account_acc, vec![Any::try_pack(cw_execute)?])
+            }
+            Self::Payout {
+                source: source_key,
+                csv,
+                chunk_size,
+                ..
+            } => {
+                let source_acc = accounts
+                    .get(source_key)
+                    .context(format!("{source_key} is not in accounts"))?;
+                let source = source_acc.address(hrp)?;
+
+                let outputs = payout_outputs(csv, accounts, contacts, chain)?;
+
+                let any_msgs = match chunk_size {
+                    Some(_) => outputs
+                        .into_iter()
+                        .map(|(address, coin)| {
+                            Ok(Any::try_pack(MsgSend {
+                                from_address: source.clone(),
+                                to_address: address,
+                                amount: vec![coin],
+                            })?)
+                        })
+                        .collect::<Result<Vec<_>>>()?,
+                    None => vec![Any::try_pack(crate::msg::multi_send(&source, &outputs))?],
+                };
+
+                (source_acc, any_msgs)
+            }
+            Self::Raw { signer, messages } => {
+                let signer_acc = accounts
+                    .get(signer)
+                    .context(format!("{signer} is not in accounts"))?;
+
+                let any_msgs = messages
+                    .iter()
+                    .map(|json| crate::msg::any_msgs_from_json(json))
+                    .collect::<Result<Vec<_>>>()?;
+
+                (signer_acc, any_msgs.concat())
+            }
+            Self::Batch { plan, .. } => {
+                let mut owner: Option<&Account> = None;
+                let mut any_msgs = vec![];
+
+                for entry in &plan.0 {
+                    let (entry_owner, entry_msgs) = Box::pin(entry.any_msgs(
+                        accounts,
+                        contacts,
+                        chain,
+                        fee_amount,
+                        rpc_endpoint,
+                        authz,
+                    ))
                     .await?;
 
-                    let unsigned_tx = crate::txs::update_tx_with_gas(
-                        unsigned_tx,
-                        needed_gas + (needed_gas >> 2),
-                    )?;
+                    if let Some(owner) = owner {
+                        (owner.address(hrp)? == entry_owner.address(hrp)?)
+                            .then_some(())
+                            .context("all messages of a batch need the same signer")?;
+                    }
 
-                    let signed_tx = owner
-                        .sign_unsigned_transaction(&unsigned_tx, chain_id, account_number)
-                        .await?;
+                    owner = Some(entry_owner);
+                    any_msgs.extend(entry_msgs);
+                }
 
-                    println!("{}", serde_json::to_string_pretty(&signed_tx)?);
+                (owner.context("empty batch plan")?, any_msgs)
+            }
+        })
+    }
+}
 
-                    Result::Ok(signed_tx)
-                }
-            })
-            .filter_map(|x| async { x.ok() })
-            .boxed_local()
-            .next()
-            .await
-            .expect("not able to sign and add gas fee tx");
-
-        println!("{}", serde_json::to_string_pretty(&signed_tx)?);
-
-        if !dry_run {
-            let _rpc_result = futures::stream::iter(rpc_endpoints.iter())
-                .then(|(_, rpc_endpoint)| {
-                    let signed_tx = signed_tx.clone();
-                    async move {
-                        println!("broadcasting trying with {}", &rpc_endpoint);
-                        let resp = tokio::time::timeout(
-                            std::time::Duration::from_secs(5),
-                            crate::broadcast::broadcast_via_tendermint_rpc(
-                                rpc_endpoint,
-                                &signed_tx,
-                            ),
-                        )
-                        .await??;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_under_max_gas() {
+        let msgs_with_gas = vec![(1, 40), (2, 50), (3, 20), (4, 70), (5, 30)];
+        assert_eq!(
+            chunk_by_gas(msgs_with_gas, 100),
+            vec![vec![1, 2], vec![3, 4], vec![5]]
+        );
+    }
 
-                        println!("{resp:?}");
+    #[test]
+    fn keeps_chunk_at_max_gas() {
+        let msgs_with_gas = vec![(1, 60), (2, 40), (3, 1)];
+        assert_eq!(chunk_by_gas(msgs_with_gas, 100), vec![vec![1, 2], vec![3]]);
+    }
 
-                        (resp.code.is_ok())
-                            .then_some(rpc_endpoint)
-                            .context("this endpoint does not work")?;
-                        Result::Ok(())
-                    }
-                })
-                .filter_map(|x| async { x.ok() })
-                .boxed_local()
-                .next()
-                .await;
-            // if rpc_result.is_none() {
-            //     futures::stream::iter(["https://api-meme-1.meme.sx"])
-            //         .then(|rest_endpoint| {
-            //             let signed_tx = signed_tx.clone();
-            //             async move {
-            //                 println!("broadcasting trying with rest {}", &rest_endpoint);
-            //                 let resp = tokio::time::timeout(
-            //                     std::time::Duration::from_secs(5),
-            //                     crate::broadcast::broadcast_via_rest(rest_endpoint, &signed_tx),
-            //                 )
-            //                 .await??;
-
-            //                 println!("{:?}", resp);
-
-            //                 (resp.code == 0).then(|| rest_endpoint).context(
-            //                     "this endpoint does not work"
-            //                 )?;
-            //                 Result::Ok(())
-            //             }
-            //         })
-            //         .filter_map(|x| async { x.ok() })
-            //         .boxed_local()
-            //         .next()
-            //         .await
-            //         .expect("not able to broadcast");
-            // }
-        }
+    #[test]
+    fn gives_large_message_its_own_chunk() {
+        let msgs_with_gas = vec![(1, 30), (2, 150), (3, 30)];
+        assert_eq!(
+            chunk_by_gas(msgs_with_gas, 100),
+            vec![vec![1], vec![2], vec![3]]
+        );
+    }
 
-        Ok(())
+    #[test]
+    fn chunks_nothing() {
+        assert_eq!(
+            chunk_by_gas(Vec::<(u8, u64)>::new(), 100),
+            Vec::<Vec<u8>>::new()
+        );
     }
 }
@@ -1,11 +1,23 @@
-use std::io::BufRead;
+use std::io::{BufRead, Read};
 use std::str::FromStr;
 
 use anyhow::Context;
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use bip32::DerivationPath;
 use cosmos_sdk_proto::cosmos::base::v1beta1::Coin;
 use cosmos_sdk_proto::cosmos::gov::v1beta1::VoteOption;
+use cosmos_sdk_proto::cosmos::staking::v1beta1::Description;
+use cosmos_sdk_proto::prost_wkt_types::MessageSerde;
+use serde::Deserialize;
 
 use crate::account::KeyStoreBackend;
+use crate::chain::FeeTier;
+use crate::data::Broadcast;
+use crate::txs::{set_sequence, TxFile};
+use crate::utils::{
+    parse_legacy_dec, read_data_from_json, read_from_base64, write_base64_to_file,
+    write_data_as_json,
+};
 use crate::Result;
 
 pub fn custom_keystorebackend(backend_str: &str) -> Result<KeyStoreBackend> {
@@ -17,16 +29,102 @@ pub fn custom_keystorebackend(backend_str: &str) -> Result<KeyStoreBackend> {
             .and_then(|(t, k)| match t {
                 "Os" => Some(KeyStoreBackend::Os(k.into())),
                 "Memory" => Some(KeyStoreBackend::Memory(k.into())),
+                "File" => Some(KeyStoreBackend::File(k.into())),
                 _ => None,
             })
             .context("invalid memory")?
     })
 }
 
-#[derive(Debug, Clone)]
+#[derive(clap::Args, Debug, Clone)]
+pub struct HdPathArgs {
+    /// defaults to 0
+    #[clap(long)]
+    pub account: Option<u32>,
+    /// defaults to 0
+    #[clap(long)]
+    pub change: Option<u32>,
+    /// defaults to 0
+    #[clap(long)]
+    pub index: Option<u32>,
+    /// full derivation path, overrides `--account`, `--change` and `--index`
+    #[clap(long)]
+    pub hd_path: Option<String>,
+}
+
+impl HdPathArgs {
+    pub fn derivation_path(&self, coin_type: u64) -> Result<DerivationPath> {
+        let hd_path = match &self.hd_path {
+            Some(hd_path) => hd_path.clone(),
+            None => crate::keys::hd_path(
+                coin_type,
+                self.account.unwrap_or_default(),
+                self.change.unwrap_or_default(),
+                self.index.unwrap_or_default(),
+            ),
+        };
+        Ok(DerivationPath::from_str(&hd_path)?)
+    }
+
+    /// Whether any of the flags is given.
+    pub fn is_set(&self) -> bool {
+        self.account.is_some()
+            || self.change.is_some()
+            || self.index.is_some()
+            || self.hd_path.is_some()
+    }
+}
+
+pub fn bip39_passphrase(prompt: bool) -> Result<String> {
+    Ok(if prompt {
+        rpassword::prompt_password("BIP39 passphrase 🔑: ").context("unable to read")?
+    } else {
+        String::new()
+    })
+}
+
+/// Vote options with their weights in `LegacyDec` integers, like `Yes` or
+/// `Yes=0.7,Abstain=0.3`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct WeightedVote(pub Vec<(VoteOption, u128)>);
+
+impl FromStr for WeightedVote {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let options = s
+            .split(',')
+            .map(|option| {
+                let (option, weight) = match option.split_once('=') {
+                    Some((option, weight)) => (option, parse_legacy_dec(weight)?),
+                    None => (option, parse_legacy_dec("1")?),
+                };
+                Ok((serde_json::from_str(&format!("\"{option}\""))?, weight))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        (options.iter().map(|(_, weight)| weight).sum::<u128>() == parse_legacy_dec("1")?)
+            .then_some(())
+            .context("weights of a vote should add up to 1")?;
+
+        Ok(Self(options))
+    }
+}
+
+impl TryFrom<String> for WeightedVote {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
 pub struct VotePair {
     pub proposal_id: u64,
-    pub option: VoteOption,
+    pub vote: WeightedVote,
 }
 
 impl FromStr for VotePair {
@@ -36,11 +134,150 @@ impl FromStr for VotePair {
         let (left, right) = s.split_once(':').context("error spliting into a pair")?;
         Ok(VotePair {
             proposal_id: left.parse()?,
-            option: serde_json::from_str(&format!("\"{right}\""))?,
+            vote: right.parse()?,
+        })
+    }
+}
+
+impl TryFrom<String> for VotePair {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+/// `chain-id:channel-N` hop of a packet-forward route, forwarding the packet
+/// from the chain over the channel.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct IbcHop {
+    pub chain_id: String,
+    pub channel: String,
+}
+
+impl FromStr for IbcHop {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (left, right) = s.split_once(':').context("error spliting into a pair")?;
+        Ok(IbcHop {
+            chain_id: left.into(),
+            channel: right.into(),
         })
     }
 }
 
+impl TryFrom<String> for IbcHop {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+/// `address,amount,denom` rows of a payout CSV file. Rows without a denom pay
+/// in the chain denom, and a first line of exactly `address,amount,denom` is
+/// skipped as the header. Quoted fields are not supported.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct PayoutRows(pub Vec<(String, u128, Option<String>)>);
+
+const PAYOUT_HEADER: &str = "address,amount,denom";
+
+impl PayoutRows {
+    pub fn parse(csv: &str) -> Result<Self> {
+        csv.lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(line_number, line)| {
+                !line.is_empty()
+                    && !line.starts_with('#')
+                    && !(*line_number == 1 && *line == PAYOUT_HEADER)
+            })
+            .map(|(line_number, line)| {
+                (!line.contains('"'))
+                    .then_some(())
+                    .context(format!("quoted fields at line {line_number}"))?;
+                let columns = line.split(',').map(str::trim).collect::<Vec<_>>();
+                let (address, amount, denom) = match columns[..] {
+                    [address, amount] => (address, amount, ""),
+                    [address, amount, denom] => (address, amount, denom),
+                    _ => anyhow::bail!(
+                        "expected address,amount[,denom] at line {line_number}, got {line}"
+                    ),
+                };
+                (!address.is_empty())
+                    .then_some(())
+                    .context(format!("no address at line {line_number}"))?;
+                let amount = amount
+                    .parse()
+                    .context(format!("invalid amount at line {line_number}"))?;
+                let denom = (!denom.is_empty()).then(|| denom.to_string());
+                Ok((address.to_string(), amount, denom))
+            })
+            .collect::<Result<_>>()
+            .map(Self)
+    }
+}
+
+impl FromStr for PayoutRows {
+    type Err = anyhow::Error;
+
+    fn from_str(path: &str) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+}
+
+impl TryFrom<String> for PayoutRows {
+    type Error = anyhow::Error;
+
+    fn try_from(path: String) -> Result<Self> {
+        path.parse()
+    }
+}
+
+/// Validator description. `edit-validator` keeps the fields which are not given.
+#[derive(clap::Args, Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ValidatorDescription {
+    #[clap(long)]
+    pub moniker: Option<String>,
+    #[clap(long)]
+    pub identity: Option<String>,
+    #[clap(long)]
+    pub website: Option<String>,
+    #[clap(long)]
+    pub security_contact: Option<String>,
+    #[clap(long)]
+    pub details: Option<String>,
+}
+
+impl ValidatorDescription {
+    /// Description with `missing` in place of the fields which are not given.
+    pub fn to_proto(&self, missing: &str) -> Description {
+        let field = |field: &Option<String>| field.clone().unwrap_or_else(|| missing.into());
+        Description {
+            moniker: field(&self.moniker),
+            identity: field(&self.identity),
+            website: field(&self.website),
+            security_contact: field(&self.security_contact),
+            details: field(&self.details),
+        }
+    }
+}
+
+/// Deserializes a coin from its CLI form, like `1000uatom`, also into `Option<Coin>`.
+pub fn deserialize_coin<'de, D, T>(deserializer: D) -> std::result::Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: From<Coin>,
+{
+    custom_coin(&String::deserialize(deserializer)?)
+        .map(T::from)
+        .map_err(serde::de::Error::custom)
+}
+
 pub fn custom_coin(coin_str: &str) -> Result<Coin> {
     let amount = coin_str
         .chars()
@@ -53,6 +290,22 @@ pub fn custom_coin(coin_str: &str) -> Result<Coin> {
     Ok(Coin { denom, amount })
 }
 
+/// Parses a decimal gas price, like `0.025uatom`.
+pub fn custom_gas_price(gas_price_str: &str) -> Result<(f64, String)> {
+    let amount = gas_price_str
+        .chars()
+        .take_while(|x| x.is_numeric() || *x == '.')
+        .collect::<String>();
+    let denom = gas_price_str
+        .chars()
+        .skip_while(|x| x.is_numeric() || *x == '.')
+        .collect::<String>();
+    (!denom.is_empty())
+        .then_some(())
+        .context("gas price needs a denom")?;
+    Ok((amount.parse()?, denom))
+}
+
 pub fn custom_io_string(json_str: &str) -> Result<String> {
     Ok(match json_str {
         "-" => std::io::stdin()
@@ -66,3 +319,275 @@ pub fn custom_io_string(json_str: &str) -> Result<String> {
         _ => json_str.into(),
     })
 }
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default)]
+pub enum TxEncoding {
+    #[default]
+    Json,
+    Base64,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct TxOutputArgs {
+    /// only generate the unsigned transaction, to be signed with `sign` later
+    #[clap(long)]
+    pub generate_only: bool,
+    /// file to write the unsigned transaction to, stdout if missing
+    #[clap(long, short)]
+    pub output: Option<String>,
+    #[clap(long, value_enum, default_value_t)]
+    pub encoding: TxEncoding,
+}
+
+impl TxOutputArgs {
+    /// Writes the `part`th one of several transactions to `<output>.<part>`.
+    pub fn write(&self, tx_file: &TxFile, part: Option<usize>) -> Result<()> {
+        let output = match (&self.output, part) {
+            (Some(output), Some(part)) => Some(format!("{output}.{part}")),
+            (output, _) => output.clone(),
+        };
+        write_tx_file(tx_file, output.as_deref(), self.encoding)
+    }
+}
+
+/// Signing data which is missing from base64 encoded transactions.
+#[derive(clap::Args, Debug, Clone)]
+pub struct TxFileArgs {
+    #[clap(long)]
+    pub chain_id: Option<String>,
+    #[clap(long)]
+    pub account_number: Option<u64>,
+    /// overrides the sequence of the transaction, required for base64 transactions
+    #[clap(long)]
+    pub sequence: Option<u64>,
+}
+
+impl TxFileArgs {
+    pub fn read(&self, path: &str) -> Result<TxFile> {
+        let mut tx_file = match read_data_from_json::<TxFile>(path) {
+            Ok(tx_file) => tx_file,
+            Err(_) => TxFile::new(
+                self.chain_id
+                    .as_deref()
+                    .context("base64 transactions need --chain-id")?,
+                self.account_number
+                    .context("base64 transactions need --account-number")?,
+                set_sequence(
+                    read_from_base64(&std::fs::read_to_string(path)?)?,
+                    self.sequence
+                        .context("base64 transactions need --sequence")?,
+                )?,
+            )?,
+        };
+        if let Some(chain_id) = &self.chain_id {
+            tx_file.chain_id = chain_id.clone();
+        }
+        if let Some(account_number) = self.account_number {
+            tx_file.account_number = account_number;
+        }
+        if let Some(sequence) = self.sequence {
+            tx_file.tx = set_sequence(tx_file.tx, sequence)?;
+            tx_file.sequence = sequence;
+        }
+        Ok(tx_file)
+    }
+}
+
+pub fn write_tx_file(tx_file: &TxFile, output: Option<&str>, encoding: TxEncoding) -> Result<()> {
+    match (encoding, output) {
+        (TxEncoding::Json, Some(output)) => {
+            write_data_as_json(output, tx_file)?;
+            println!("transaction is written to {output}");
+        }
+        (TxEncoding::Json, None) => println!("{}", serde_json::to_string_pretty(tx_file)?),
+        (TxEncoding::Base64, output) => {
+            match output {
+                Some(output) => {
+                    write_base64_to_file(&tx_file.tx, output)?;
+                    println!("transaction is written to {output}");
+                }
+                None => println!("{}", BASE64_STANDARD.encode(tx_file.tx.try_encoded()?)),
+            }
+            // base64 does not carry these, they have to be passed to `sign`
+            println!(
+                "chain id: {}, account number: {}, sequence: {}",
+                tx_file.chain_id, tx_file.account_number, tx_file.sequence
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Reads bytes from `@file`, stdin (`-`) or the argument itself,
+/// given as hex, base64 or raw binary.
+pub fn custom_io_bytes(input: &str) -> Result<Vec<u8>> {
+    let bytes = match input {
+        "-" => {
+            let mut bytes = vec![];
+            std::io::stdin().lock().read_to_end(&mut bytes)?;
+            bytes
+        }
+        _ if input.starts_with('@') => {
+            std::fs::read(input.strip_prefix('@').context("should never arise")?)?
+        }
+        _ => input.as_bytes().to_vec(),
+    };
+    Ok(match std::str::from_utf8(&bytes).map(str::trim) {
+        Ok(text) => hex::decode(text)
+            .or_else(|_| BASE64_STANDARD.decode(text))
+            .unwrap_or(bytes),
+        Err(_) => bytes,
+    })
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct BroadcastArgs {
+    #[clap(short = 'n', long)]
+    pub dry_run: bool,
+    /// seconds to wait for the transaction to be included in a block, 0 to not wait
+    #[clap(long, default_value_t = 60)]
+    pub wait_timeout: u64,
+    /// transport to simulate and broadcast with, before falling back to the others
+    #[clap(long, value_enum)]
+    pub via: Option<Broadcast>,
+    #[clap(long)]
+    pub rest: Option<String>,
+    #[clap(long)]
+    pub grpc: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Gas {
+    Auto,
+    Limit(u64),
+}
+
+impl FromStr for Gas {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "auto" => Self::Auto,
+            _ => Self::Limit(s.parse().context("gas must be a number or auto")?),
+        })
+    }
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct TxArgs {
+    #[clap(long, short, value_parser(custom_coin))]
+    pub fee: Option<Coin>,
+    #[clap(long, default_value = "")]
+    pub memo: String,
+    /// block height after which the transaction is not valid anymore
+    #[clap(long, conflicts_with = "timeout_blocks")]
+    pub timeout_height: Option<u64>,
+    /// sets the timeout height this many blocks after the latest one
+    #[clap(long)]
+    pub timeout_blocks: Option<u64>,
+    /// gas limit, or `auto` to simulate it
+    #[clap(long, default_value = "auto")]
+    pub gas: Gas,
+    /// multiplier of the simulated gas
+    #[clap(long, default_value_t = 1.25)]
+    pub gas_adjustment: f64,
+    /// computes the fee from this gas price, like `0.025uatom`
+    #[clap(long, value_parser(custom_gas_price), conflicts_with = "fee")]
+    pub gas_prices: Option<(f64, String)>,
+    /// tier of the gas prices in the chain config
+    #[clap(long, value_enum, default_value_t)]
+    pub fee_tier: FeeTier,
+    /// pays the fee in this denom, instead of the first one held by the payer
+    #[clap(long, conflicts_with_all = ["fee", "gas_prices"])]
+    pub fee_denom: Option<String>,
+    /// refuses to pay more than this, in the fee denom, which is the chain
+    /// denom unless `--fee-denom` is given
+    #[clap(long)]
+    pub max_fee: Option<u128>,
+}
+
+impl TxArgs {
+    pub fn adjusted_gas(&self, needed_gas: u64) -> u64 {
+        (needed_gas as f64 * self.gas_adjustment).ceil() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_single_vote() {
+        let vote: VotePair = "812:Yes".parse().unwrap();
+        assert_eq!(vote.proposal_id, 812);
+        assert_eq!(vote.vote.0, vec![(VoteOption::Yes, 10u128.pow(18))]);
+    }
+
+    #[test]
+    fn parses_weighted_vote() {
+        let vote: WeightedVote = "Yes=0.7,Abstain=0.3".parse().unwrap();
+        assert_eq!(
+            vote.0,
+            vec![
+                (VoteOption::Yes, 7 * 10u128.pow(17)),
+                (VoteOption::Abstain, 3 * 10u128.pow(17)),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_votes() {
+        for vote in ["Yes=0.7,No=0.2", "Yes=0.5,No=0.6", "Maybe", "Yes=one", ""] {
+            assert!(vote.parse::<WeightedVote>().is_err(), "{vote}");
+        }
+    }
+
+    #[test]
+    fn skips_header_on_first_line() {
+        let rows = PayoutRows::parse("address,amount,denom\ncosmos1a,100,uatom\n").unwrap();
+        assert_eq!(rows.0, vec![("cosmos1a".into(), 100, Some("uatom".into()))]);
+    }
+
+    #[test]
+    fn keeps_header_like_rows_after_first_line() {
+        let rows = PayoutRows::parse("cosmos1a,100\naddress,amount,denom\n");
+        assert!(rows.unwrap_err().to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn rejects_other_headers() {
+        assert!(PayoutRows::parse("address,amount\ncosmos1a,100\n").is_err());
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let rows = PayoutRows::parse("# team\n\ncosmos1a,100,uatom\n  # advisors\n").unwrap();
+        assert_eq!(rows.0.len(), 1);
+    }
+
+    #[test]
+    fn defaults_missing_denom() {
+        let rows = PayoutRows::parse("cosmos1a,100\ncosmos1b,200,\n").unwrap();
+        assert_eq!(
+            rows.0,
+            vec![
+                ("cosmos1a".into(), 100, None),
+                ("cosmos1b".into(), 200, None)
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_rows() {
+        for csv in [
+            "cosmos1a",
+            "cosmos1a,",
+            ",100,uatom",
+            "cosmos1a,-100,uatom",
+            "cosmos1a,100,uatom,extra",
+            "\"cosmos1a\",100,uatom",
+        ] {
+            assert!(PayoutRows::parse(csv).is_err(), "{csv}");
+        }
+    }
+}
@@ -23,8 +23,10 @@ pub struct Chain {
     pub broadcast: Broadcast,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
 pub enum Broadcast {
+    #[default]
     Rpc,
     Rest,
+    Grpc,
 }
@@ -74,6 +74,11 @@ pub async fn get_rpc_endpoints<'a>(
 }
 
 pub async fn get_cosmos_directory_name(chain_id: &str) -> Result<String> {
+    let chain_name = get_cosmos_directory_chain_name(chain_id).await?;
+    Ok(format!("https://rpc.cosmos.directory/{chain_name}"))
+}
+
+pub async fn get_cosmos_directory_chain_name(chain_id: &str) -> Result<String> {
     let resp: Value = ureq::get("https://chains.cosmos.directory")
         .call()?
         .into_json()?;
@@ -92,7 +97,7 @@ pub async fn get_cosmos_directory_name(chain_id: &str) -> Result<String> {
         .next()
         .context("at least one chain")??;
 
-    Ok(format!("https://rpc.cosmos.directory/{chain_name}"))
+    Ok(chain_name.into())
 }
 
 pub async fn get_zone_ids<'a>(graphql_endpoint: &str) -> Result<Vec<String>> {
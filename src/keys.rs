@@ -1,15 +1,20 @@
 use std::collections::HashMap;
-use std::str::FromStr;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
 use anyhow::Context;
 use base64::prelude::{Engine as _, BASE64_STANDARD};
 use bech32::{Bech32, Hrp};
 use bip32::secp256k1::ecdsa::SigningKey;
+use bip32::secp256k1::elliptic_curve::rand_core::{OsRng, RngCore};
 use bip32::{DerivationPath, Language, Mnemonic, XPrv};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
 use clap::ValueEnum;
+use cosmos_sdk_proto::prost_wkt_types::Any;
 use ripemd::Ripemd160;
 use secp256k1::{PublicKey, Secp256k1, SecretKey};
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use sha3::Keccak256;
 
@@ -20,19 +25,56 @@ use crate::Result;
 lazy_static::lazy_static! {
     /// This is an example for using doc comment attributes
     static ref MEMORY_KEYRING: Arc<Mutex<HashMap<String, Vec<u8>>>> = Arc::new(Mutex::new(HashMap::new()));
+    /// decrypted file keys, apart from the Memory backend keys
+    static ref FILE_KEY_CACHE: Arc<Mutex<HashMap<String, Vec<u8>>>> = Arc::new(Mutex::new(HashMap::new()));
 }
 
-fn key_from_mnemonic(mmseed: &str, coin: u64) -> Result<XPrv> {
+/// Consensus public key of a validator, from its `priv_validator_key.json`.
+pub fn read_consensus_public_key(path: &str) -> Result<Any> {
+    let key: serde_json::Value = crate::utils::read_data_from_json(path)?;
+    let public_key = key.get("pub_key").context("no pub_key")?;
+    let key_bytes = BASE64_STANDARD.decode(
+        public_key
+            .get("value")
+            .and_then(|value| value.as_str())
+            .context("no pub_key value")?,
+    )?;
+
+    Ok(
+        match public_key
+            .get("type")
+            .and_then(|key_type| key_type.as_str())
+        {
+            Some("tendermint/PubKeyEd25519") => {
+                Any::try_pack(cosmos_sdk_proto::cosmos::crypto::ed25519::PubKey { key: key_bytes })?
+            }
+            Some("tendermint/PubKeySecp256k1") => {
+                Any::try_pack(cosmos_sdk_proto::cosmos::crypto::secp256k1::PubKey {
+                    key: key_bytes,
+                })?
+            }
+            key_type => anyhow::bail!("unsupported consensus key type {key_type:?}"),
+        },
+    )
+}
+
+pub fn hd_path(coin_type: u64, account: u32, change: u32, index: u32) -> String {
+    format!("m/44'/{coin_type}'/{account}'/{change}/{index}")
+}
+
+fn key_from_mnemonic(
+    mmseed: &str,
+    derivation_path: &DerivationPath,
+    passphrase: &str,
+) -> Result<XPrv> {
     // let mmseed = "tip purse since square taste soccer future hat orbit blame anchor oppose onion garlic taxi daring aisle slide buzz theory bronze explain refuse surface";
     // let address = "cosmos10uuc6zj564lwhuvlutwsmsa2ruc8qmj6x8kp6x";
     println!("'{mmseed}'");
     let mnemonic = Mnemonic::new(mmseed, Language::English)?;
-    let drv_path = format!("m/44'/{coin}'/0'/0/0");
-    println!("using {drv_path}..");
-    let derivation_path = DerivationPath::from_str(&drv_path)?;
-    let seed = mnemonic.to_seed("");
+    println!("using {derivation_path}..");
+    let seed = mnemonic.to_seed(passphrase);
 
-    Ok(XPrv::derive_from_path(seed, &derivation_path)?)
+    Ok(XPrv::derive_from_path(seed, derivation_path)?)
 }
 
 pub fn cosmos_key_derive(bytes: &[u8]) -> Vec<u8> {
@@ -49,31 +91,53 @@ pub fn ethereum_key_derive(bytes: &[u8]) -> Vec<u8> {
         .collect()
 }
 
-#[derive(Debug, Clone, ValueEnum)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, ValueEnum)]
 pub enum AddressType {
+    #[default]
     Cosmos,
+    /// Ethermint `eth_secp256k1` keys, as used by Evmos or Cronos
     Ethereum,
+    /// Injective `eth_secp256k1` keys, which are Ethermint keys under other type URLs
+    Injective,
+}
+
+impl AddressType {
+    pub fn coin_type(&self) -> u64 {
+        match self {
+            Self::Cosmos => 118,
+            Self::Ethereum | Self::Injective => 60,
+        }
+    }
+
+    pub fn default_hd_path(&self) -> String {
+        hd_path(self.coin_type(), 0, 0, 0)
+    }
 }
 
 pub fn from_pk_bytes_to_address(pub_key: &[u8], addr_type: &AddressType) -> Result<String> {
     let data = match addr_type {
         AddressType::Cosmos => cosmos_key_derive(pub_key),
-        AddressType::Ethereum => ethereum_key_derive(pub_key),
+        AddressType::Ethereum | AddressType::Injective => ethereum_key_derive(pub_key),
     };
     let address = bech32::encode::<Bech32>(Hrp::parse("cosmos")?, &data)?;
     println!("Cosmos address: {}", &address);
     Ok(address)
 }
 
-fn save_key_to_os(bytes: &[u8], keyname: &str) -> Result<()> {
+pub fn save_key_to_os(bytes: &[u8], keyname: &str) -> Result<()> {
     let entry = keyring::Entry::new("rover", keyname)?;
     let password = BASE64_STANDARD.encode(bytes);
     entry.set_password(&password)?;
     Ok(())
 }
 
-pub fn save_key_to_os_from_mmseed(mmseed: &str, keyname: &str, coin: u64) -> Result<()> {
-    let priv_key = crate::keys::key_from_mnemonic(mmseed, coin)?;
+pub fn save_key_to_os_from_mmseed(
+    mmseed: &str,
+    keyname: &str,
+    derivation_path: &DerivationPath,
+    passphrase: &str,
+) -> Result<()> {
+    let priv_key = crate::keys::key_from_mnemonic(mmseed, derivation_path, passphrase)?;
     save_key_to_os(&priv_key.to_bytes(), keyname)?;
     println!(
         "{}",
@@ -103,8 +167,13 @@ pub fn save_key_to_memory(bytes: &[u8], name: &str) -> Result<()> {
     Ok(())
 }
 
-pub fn save_key_to_memory_from_mmseed(mmseed: &str, name: &str, coin: u64) -> Result<()> {
-    let priv_key = crate::keys::key_from_mnemonic(mmseed, coin)?;
+pub fn save_key_to_memory_from_mmseed(
+    mmseed: &str,
+    name: &str,
+    derivation_path: &DerivationPath,
+    passphrase: &str,
+) -> Result<()> {
+    let priv_key = crate::keys::key_from_mnemonic(mmseed, derivation_path, passphrase)?;
     save_key_to_memory(&priv_key.to_bytes(), name)?;
     Ok(())
 }
@@ -126,14 +195,209 @@ pub fn get_uncompressed_pub_key_from_memory(key_name: &str) -> Result<Vec<u8>> {
     Ok(public_key.serialize_uncompressed()[1..].to_vec())
 }
 
-pub fn mnemonic_to_cosmos_addr(mnemonic: &Mnemonic, hrp: &str, coin_type: &u64) -> Result<String> {
-    let drv_path = format!("m/44'/{coin_type}'/0'/0/0");
-    let derivation_path = DerivationPath::from_str(&drv_path)?;
-    let seed = mnemonic.to_seed("");
-    let priv_key = XPrv::derive_from_path(seed, &derivation_path)?;
+// scrypt parameters recommended for interactive logins
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+/// Passphrase-encrypted private key, as stored by the `File` keystore backend.
+#[derive(Serialize, Deserialize, Debug)]
+struct EncryptedKeyFile {
+    kdf: String,
+    log_n: u8,
+    r: u32,
+    p: u32,
+    salt: String,
+    cipher: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl EncryptedKeyFile {
+    fn encrypt(bytes: &[u8], passphrase: &str) -> Result<Self> {
+        let mut salt = [0; 16];
+        OsRng.fill_bytes(&mut salt);
+        let mut nonce = [0; 12];
+        OsRng.fill_bytes(&mut nonce);
+
+        let cipher = key_file_cipher(passphrase, &salt, SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P)?;
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), bytes)
+            .map_err(|e| anyhow::anyhow!("encryption failed: {e}"))?;
+
+        Ok(Self {
+            kdf: "scrypt".into(),
+            log_n: SCRYPT_LOG_N,
+            r: SCRYPT_R,
+            p: SCRYPT_P,
+            salt: BASE64_STANDARD.encode(salt),
+            cipher: "chacha20poly1305".into(),
+            nonce: BASE64_STANDARD.encode(nonce),
+            ciphertext: BASE64_STANDARD.encode(ciphertext),
+        })
+    }
+
+    fn decrypt(&self, passphrase: &str) -> Result<Vec<u8>> {
+        let cipher = key_file_cipher(
+            passphrase,
+            &BASE64_STANDARD.decode(&self.salt)?,
+            self.log_n,
+            self.r,
+            self.p,
+        )?;
+        cipher
+            .decrypt(
+                Nonce::from_slice(&BASE64_STANDARD.decode(&self.nonce)?),
+                BASE64_STANDARD.decode(&self.ciphertext)?.as_slice(),
+            )
+            .map_err(|_| anyhow::anyhow!("wrong passphrase or corrupted key file"))
+    }
+}
+
+fn key_file_path(key_name: &str) -> Result<PathBuf> {
+    let project_dir =
+        directories::ProjectDirs::from("systems", "rnbguy", "rover").context("project dir")?;
+    let keys_dir = project_dir.data_local_dir().join("keys");
+    std::fs::create_dir_all(&keys_dir)?;
+    Ok(keys_dir.join(format!("{key_name}.json")))
+}
+
+fn key_file_passphrase(confirm: bool) -> Result<String> {
+    // headless machines can't answer a prompt
+    if let Ok(passphrase) = std::env::var("ROVER_KEYSTORE_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+    let passphrase =
+        rpassword::prompt_password("Keystore passphrase 🔒: ").context("unable to read")?;
+    if confirm {
+        let repeated =
+            rpassword::prompt_password("Repeat passphrase 🔒: ").context("unable to read")?;
+        (passphrase == repeated)
+            .then_some(())
+            .context("passphrases do not match")?;
+    }
+    Ok(passphrase)
+}
+
+fn key_file_cipher(
+    passphrase: &str,
+    salt: &[u8],
+    log_n: u8,
+    r: u32,
+    p: u32,
+) -> Result<ChaCha20Poly1305> {
+    let params = scrypt::Params::new(log_n, r, p, 32)
+        .map_err(|e| anyhow::anyhow!("invalid scrypt params: {e}"))?;
+    let mut key = [0; 32];
+    scrypt::scrypt(passphrase.as_bytes(), salt, &params, &mut key)
+        .map_err(|e| anyhow::anyhow!("scrypt failed: {e}"))?;
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+pub fn save_key_to_file(bytes: &[u8], key_name: &str) -> Result<()> {
+    let path = key_file_path(key_name)?;
+    (!path.exists())
+        .then_some(())
+        .context(format!("{} already exists", path.display()))?;
+
+    let passphrase = key_file_passphrase(true)?;
+    let key_file = EncryptedKeyFile::encrypt(bytes, &passphrase)?;
+
+    crate::utils::write_private_file(&path, serde_json::to_string_pretty(&key_file)?.as_bytes())?;
+    println!("Saved to {}", path.display());
+    Ok(())
+}
+
+pub fn save_key_to_file_from_mmseed(
+    mmseed: &str,
+    key_name: &str,
+    derivation_path: &DerivationPath,
+    passphrase: &str,
+) -> Result<()> {
+    let priv_key = crate::keys::key_from_mnemonic(mmseed, derivation_path, passphrase)?;
+    save_key_to_file(&priv_key.to_bytes(), key_name)?;
+    Ok(())
+}
+
+fn get_priv_bytes_from_file(key_name: &str) -> Result<Vec<u8>> {
+    // decrypted keys are cached, so a single run asks for the passphrase only once
+    if let Some(priv_bytes) = FILE_KEY_CACHE.lock().expect("Error").get(key_name) {
+        return Ok(priv_bytes.clone());
+    }
+
+    let path = key_file_path(key_name)?;
+    let key_file: EncryptedKeyFile = serde_json::from_str(
+        &std::fs::read_to_string(&path).context(format!("{} is not present", path.display()))?,
+    )?;
+
+    (key_file.kdf == "scrypt" && key_file.cipher == "chacha20poly1305")
+        .then_some(())
+        .context("unsupported key file format")?;
+
+    let passphrase = key_file_passphrase(false)?;
+    let priv_bytes = key_file.decrypt(&passphrase)?;
+
+    FILE_KEY_CACHE
+        .lock()
+        .expect("Error")
+        .insert(key_name.into(), priv_bytes.clone());
+    Ok(priv_bytes)
+}
+
+pub fn get_priv_key_from_file(key_name: &str) -> Result<SigningKey> {
+    let priv_bytes = get_priv_bytes_from_file(key_name)?;
+    Ok(SigningKey::from_slice(&priv_bytes).expect("error"))
+}
+
+pub fn get_uncompressed_pub_key_from_file(key_name: &str) -> Result<Vec<u8>> {
+    let priv_bytes = get_priv_bytes_from_file(key_name)?;
+    let secp = Secp256k1::new();
+    let secret_key = SecretKey::from_slice(&priv_bytes).expect("32 bytes, within curve order");
+    let public_key = PublicKey::from_secret_key(&secp, &secret_key);
+    Ok(public_key.serialize_uncompressed()[1..].to_vec())
+}
+
+pub fn mnemonic_to_cosmos_addr(
+    mnemonic: &Mnemonic,
+    hrp: &str,
+    derivation_path: &DerivationPath,
+    passphrase: &str,
+) -> Result<String> {
+    let seed = mnemonic.to_seed(passphrase);
+    let priv_key = XPrv::derive_from_path(seed, derivation_path)?;
     let pub_key = priv_key.public_key();
     Ok(bech32::encode::<Bech32>(
         Hrp::parse(hrp)?,
         &cosmos_key_derive(pub_key.to_bytes().as_ref()),
     )?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRIV_KEY: [u8; 32] = [7; 32];
+
+    #[test]
+    fn key_file_round_trips() {
+        let key_file = EncryptedKeyFile::encrypt(&PRIV_KEY, "passphrase").unwrap();
+        let json = serde_json::to_string_pretty(&key_file).unwrap();
+        let key_file: EncryptedKeyFile = serde_json::from_str(&json).unwrap();
+        assert_eq!(key_file.decrypt("passphrase").unwrap(), PRIV_KEY);
+    }
+
+    #[test]
+    fn key_file_rejects_wrong_passphrase() {
+        let key_file = EncryptedKeyFile::encrypt(&PRIV_KEY, "passphrase").unwrap();
+        assert!(key_file.decrypt("other passphrase").is_err());
+    }
+
+    #[test]
+    fn key_file_rejects_tampered_ciphertext() {
+        let mut key_file = EncryptedKeyFile::encrypt(&PRIV_KEY, "passphrase").unwrap();
+        let mut ciphertext = BASE64_STANDARD.decode(&key_file.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        key_file.ciphertext = BASE64_STANDARD.encode(ciphertext);
+        assert!(key_file.decrypt("passphrase").is_err());
+    }
+}
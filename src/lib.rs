@@ -1,6 +1,7 @@
 // https://iancoleman.io/bip39
 
 pub mod account;
+pub mod armor;
 pub mod broadcast;
 pub mod chain;
 pub mod cli;
@@ -9,7 +10,11 @@ pub mod endpoint;
 pub mod keys;
 pub mod ledger;
 pub mod msg;
+pub mod multisig;
+pub mod packet;
+pub mod proto;
 pub mod query;
+pub mod sequence;
 pub mod txs;
 pub mod utils;
 pub mod vanity;
@@ -2,23 +2,32 @@ use anyhow::Context;
 use cosmos_sdk_proto::cosmos::authz::v1beta1::{
     GenericAuthorization, Grant, MsgExec, MsgGrant, MsgRevoke,
 };
-use cosmos_sdk_proto::cosmos::bank::v1beta1::MsgSend;
+use cosmos_sdk_proto::cosmos::bank::v1beta1::{Input, MsgMultiSend, MsgSend, Output};
 use cosmos_sdk_proto::cosmos::base::v1beta1::Coin;
 use cosmos_sdk_proto::cosmos::distribution::v1beta1::{
-    MsgWithdrawDelegatorReward, QueryDelegationTotalRewardsRequest,
+    MsgWithdrawDelegatorReward, MsgWithdrawValidatorCommission, QueryDelegationTotalRewardsRequest,
     QueryDelegationTotalRewardsResponse,
 };
 use cosmos_sdk_proto::cosmos::feegrant::v1beta1::{
     BasicAllowance, MsgGrantAllowance, MsgRevokeAllowance,
 };
-use cosmos_sdk_proto::cosmos::gov::v1beta1::MsgVote;
+use cosmos_sdk_proto::cosmos::gov::v1;
+use cosmos_sdk_proto::cosmos::gov::v1beta1::{
+    MsgVote, MsgVoteWeighted, VoteOption, WeightedVoteOption,
+};
+use cosmos_sdk_proto::cosmos::slashing::v1beta1::MsgUnjail;
 use cosmos_sdk_proto::cosmos::staking::v1beta1::stake_authorization::{Policy, Validators};
 use cosmos_sdk_proto::cosmos::staking::v1beta1::{
-    AuthorizationType, MsgDelegate, StakeAuthorization,
+    AuthorizationType, MsgDelegate, MsgEditValidator, StakeAuthorization,
 };
+use cosmos_sdk_proto::ibc::core::client::v1::Height;
 use cosmos_sdk_proto::prost_wkt_types::{Any, MessageSerde};
+use prost::Message;
+use serde_json::Value;
 
+use crate::proto::{MsgTransfer, IBC_MSG_TRANSFER_TYPE_URL};
 use crate::query::perform_rpc_query;
+use crate::utils::format_legacy_dec;
 use crate::Result;
 
 fn after_one_year() -> Result<chrono::NaiveDateTime> {
@@ -81,6 +90,36 @@ pub fn local_token_transfer(
     })
 }
 
+/// One transfer from `source` to many addresses.
+pub fn multi_send(source: &str, outputs: &[(String, Coin)]) -> MsgMultiSend {
+    // input coins have to be sorted by denom, without duplicates
+    let mut totals = std::collections::BTreeMap::<&str, u128>::new();
+    for (_, coin) in outputs {
+        *totals.entry(coin.denom.as_str()).or_default() +=
+            coin.amount.parse::<u128>().unwrap_or_default();
+    }
+
+    MsgMultiSend {
+        inputs: vec![Input {
+            address: source.into(),
+            coins: totals
+                .into_iter()
+                .map(|(denom, amount)| Coin {
+                    denom: denom.into(),
+                    amount: amount.to_string(),
+                })
+                .collect(),
+        }],
+        outputs: outputs
+            .iter()
+            .map(|(address, coin)| Output {
+                address: address.clone(),
+                coins: vec![coin.clone()],
+            })
+            .collect(),
+    }
+}
+
 pub fn unit_transfer(granter: &str, grantee: &str, denom: &str) -> Result<MsgSend> {
     local_token_transfer(granter, grantee, 1, denom)
 }
@@ -182,6 +221,31 @@ pub fn generate_usual_revoke(granter: &str, grantee: &str) -> Result<Vec<MsgRevo
     )
 }
 
+/// Operator messages, for operator keys which stay offline.
+pub fn generate_operator_auth(granter: &str, grantee: &str) -> Result<Vec<MsgGrant>> {
+    generate_authz_msgs(
+        granter,
+        grantee,
+        &[
+            MsgWithdrawValidatorCommission::default().type_url(),
+            MsgUnjail::default().type_url(),
+            MsgEditValidator::default().type_url(),
+        ],
+    )
+}
+
+pub fn generate_operator_revoke(granter: &str, grantee: &str) -> Result<Vec<MsgRevoke>> {
+    generate_authz_revoke_msgs(
+        granter,
+        grantee,
+        &[
+            MsgWithdrawValidatorCommission::default().type_url(),
+            MsgUnjail::default().type_url(),
+            MsgEditValidator::default().type_url(),
+        ],
+    )
+}
+
 pub fn delegate_to(amount: u128, denom: &str, validator: &str, delegator: &str) -> MsgDelegate {
     MsgDelegate {
         delegator_address: delegator.into(),
@@ -192,3 +256,166 @@ pub fn delegate_to(amount: u128, denom: &str, validator: &str, delegator: &str)
         }),
     }
 }
+
+/// Packs proto JSON messages with `@type`, given one by one or as a JSON array.
+pub fn any_msgs_from_json(json: &str) -> Result<Vec<Any>> {
+    let values = match serde_json::from_str(json)? {
+        serde_json::Value::Array(values) => values,
+        value => vec![value],
+    };
+    values
+        .into_iter()
+        .map(|value| {
+            let type_url = value
+                .get("@type")
+                .and_then(|type_url| type_url.as_str())
+                .context("message has no @type")?
+                .to_string();
+            serde_json::from_value(value).context(format!("not able to parse {type_url}"))
+        })
+        .collect()
+}
+
+/// Vote of `voter`, weighted unless it is a single option.
+pub fn generate_vote(
+    gov_v1: bool,
+    proposal_id: u64,
+    voter: &str,
+    options: &[(VoteOption, u128)],
+) -> Result<Any> {
+    let weight_one = 10u128.pow(18);
+    Ok(match (gov_v1, options) {
+        (true, [(option, weight)]) if *weight == weight_one => Any::try_pack(v1::MsgVote {
+            proposal_id,
+            voter: voter.into(),
+            option: (*option).into(),
+            metadata: String::new(),
+        })?,
+        (false, [(option, weight)]) if *weight == weight_one => Any::try_pack(MsgVote {
+            proposal_id,
+            voter: voter.into(),
+            option: (*option).into(),
+        })?,
+        (true, _) => Any::try_pack(v1::MsgVoteWeighted {
+            proposal_id,
+            voter: voter.into(),
+            options: options
+                .iter()
+                .map(|(option, weight)| v1::WeightedVoteOption {
+                    option: (*option).into(),
+                    weight: format_legacy_dec(*weight),
+                })
+                .collect(),
+            metadata: String::new(),
+        })?,
+        // v1beta1 weights are `LegacyDec`s, which are encoded as their integers
+        (false, _) => Any::try_pack(MsgVoteWeighted {
+            proposal_id,
+            voter: voter.into(),
+            options: options
+                .iter()
+                .map(|(option, weight)| WeightedVoteOption {
+                    option: (*option).into(),
+                    weight: weight.to_string(),
+                })
+                .collect(),
+        })?,
+    })
+}
+
+/// `MsgTransfer` over the `transfer` port, packed by hand for its `memo`.
+pub fn ibc_transfer(
+    channel: &str,
+    token: Coin,
+    sender: &str,
+    receiver: &str,
+    timeout_height: Option<Height>,
+    timeout_timestamp: u64,
+    memo: &str,
+) -> Any {
+    Any {
+        type_url: IBC_MSG_TRANSFER_TYPE_URL.into(),
+        value: MsgTransfer {
+            source_port: "transfer".into(),
+            source_channel: channel.into(),
+            token: Some(token),
+            sender: sender.into(),
+            receiver: receiver.into(),
+            timeout_height,
+            timeout_timestamp,
+            memo: memo.into(),
+        }
+        .encode_to_vec(),
+    }
+}
+
+/// Packet-forward-middleware memo, from the (receiver, channel) of each hop.
+/// `next` is passed along to the last hop, e.g. an IBC hooks memo.
+pub fn forward_memo(hops: &[(String, String)], next: Option<Value>) -> Option<Value> {
+    hops.iter().rev().fold(next, |next, (receiver, channel)| {
+        let mut forward = serde_json::json!({
+            "receiver": receiver,
+            "port": "transfer",
+            "channel": channel,
+        });
+        if let Some(next) = next {
+            forward["next"] = next;
+        }
+        Some(serde_json::json!({ "forward": forward }))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn forward_memo_nests_hops() {
+        let hops = [
+            ("osmo1receiver".to_string(), "channel-141".to_string()),
+            ("juno1receiver".to_string(), "channel-42".to_string()),
+        ];
+        assert_eq!(
+            forward_memo(&hops, None),
+            Some(json!({
+                "forward": {
+                    "receiver": "osmo1receiver",
+                    "port": "transfer",
+                    "channel": "channel-141",
+                    "next": {
+                        "forward": {
+                            "receiver": "juno1receiver",
+                            "port": "transfer",
+                            "channel": "channel-42",
+                        }
+                    }
+                }
+            }))
+        );
+    }
+
+    #[test]
+    fn forward_memo_passes_next_to_last_hop() {
+        let hops = [("osmo1receiver".to_string(), "channel-141".to_string())];
+        let next = json!({ "wasm": { "contract": "osmo1contract", "msg": {} } });
+        assert_eq!(
+            forward_memo(&hops, Some(next.clone())),
+            Some(json!({
+                "forward": {
+                    "receiver": "osmo1receiver",
+                    "port": "transfer",
+                    "channel": "channel-141",
+                    "next": next,
+                }
+            }))
+        );
+    }
+
+    #[test]
+    fn forward_memo_without_hops() {
+        assert_eq!(forward_memo(&[], None), None);
+        assert_eq!(forward_memo(&[], Some(json!({}))), Some(json!({})));
+    }
+}
@@ -0,0 +1,227 @@
+use anyhow::Context;
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use bech32::{Bech32, Hrp};
+use bip32::secp256k1::ecdsa::signature::Verifier;
+use bip32::secp256k1::ecdsa::{Signature, VerifyingKey};
+use cosmos_sdk_proto::cosmos::crypto::multisig::v1beta1::{CompactBitArray, MultiSignature};
+use cosmos_sdk_proto::cosmos::crypto::multisig::LegacyAminoPubKey;
+use cosmos_sdk_proto::cosmos::crypto::secp256k1::PubKey;
+use cosmos_sdk_proto::cosmos::tx::signing::v1beta1::SignMode;
+use cosmos_sdk_proto::cosmos::tx::v1beta1::mode_info::{Multi, Single, Sum};
+use cosmos_sdk_proto::cosmos::tx::v1beta1::{ModeInfo, Tx};
+use cosmos_sdk_proto::prost_wkt_types::Any;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::keys::cosmos_key_derive;
+use crate::Result;
+
+// amino prefixes of `tendermint/PubKeyMultisigThreshold` and `tendermint/PubKeySecp256k1`
+const AMINO_MULTISIG_PREFIX: [u8; 4] = [0x22, 0xc1, 0xf7, 0xe2];
+const AMINO_SECP256K1_PREFIX: [u8; 4] = [0xeb, 0x5a, 0xe9, 0x87];
+
+/// Signature of a single multisig member over the legacy amino JSON sign doc.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PartialSignature {
+    pub public_key: String,
+    pub signature: String,
+}
+
+/// Sorts member keys by their addresses, as `keys add --multisig` does.
+pub fn sort_public_keys(public_keys: &mut [Vec<u8>]) {
+    public_keys.sort_by_key(|key| cosmos_key_derive(key));
+}
+
+fn amino_encode(threshold: u32, public_keys: &[Vec<u8>]) -> Vec<u8> {
+    let mut bytes = AMINO_MULTISIG_PREFIX.to_vec();
+
+    bytes.push(0x08);
+    prost::encoding::encode_varint(threshold.into(), &mut bytes);
+
+    for key in public_keys {
+        let mut key_bytes = AMINO_SECP256K1_PREFIX.to_vec();
+        prost::encoding::encode_varint(key.len() as u64, &mut key_bytes);
+        key_bytes.extend(key);
+
+        bytes.push(0x12);
+        prost::encoding::encode_varint(key_bytes.len() as u64, &mut bytes);
+        bytes.extend(key_bytes);
+    }
+
+    bytes
+}
+
+pub fn multisig_address(threshold: u32, public_keys: &[Vec<u8>], prefix: &str) -> Result<String> {
+    let data = &Sha256::digest(amino_encode(threshold, public_keys))[..20];
+    Ok(bech32::encode::<Bech32>(Hrp::parse(prefix)?, data)?)
+}
+
+pub fn multisig_public_key(threshold: u32, public_keys: &[Vec<u8>]) -> Result<LegacyAminoPubKey> {
+    Ok(LegacyAminoPubKey {
+        threshold,
+        public_keys: public_keys
+            .iter()
+            .map(|key| Ok(Any::try_pack(PubKey { key: key.clone() })?))
+            .collect::<Result<_>>()?,
+    })
+}
+
+fn compact_bit_array(bits: &[bool]) -> CompactBitArray {
+    let mut elems = vec![0; (bits.len() + 7) / 8];
+    for (i, _) in bits.iter().enumerate().filter(|(_, bit)| **bit) {
+        elems[i / 8] |= 1 << (7 - (i % 8));
+    }
+    CompactBitArray {
+        extra_bits_stored: (bits.len() % 8) as u32,
+        elems,
+    }
+}
+
+pub fn combine_signatures(
+    unsigned_tx: &Tx,
+    threshold: u32,
+    public_keys: &[Vec<u8>],
+    partial_signatures: &[PartialSignature],
+    sign_bytes: &[u8],
+) -> Result<Tx> {
+    let mut bits = vec![];
+    let mut signatures = vec![];
+
+    for key in public_keys {
+        let encoded_key = BASE64_STANDARD.encode(key);
+        let partial = partial_signatures
+            .iter()
+            .find(|partial| partial.public_key == encoded_key);
+
+        bits.push(partial.is_some());
+
+        if let Some(partial) = partial {
+            let signature = BASE64_STANDARD.decode(&partial.signature)?;
+            VerifyingKey::from_sec1_bytes(key)
+                .map_err(|e| anyhow::anyhow!("invalid member key: {e}"))?
+                .verify(
+                    sign_bytes,
+                    &Signature::from_slice(&signature)
+                        .map_err(|e| anyhow::anyhow!("invalid signature: {e}"))?,
+                )
+                .map_err(|_| anyhow::anyhow!("signature of {encoded_key} does not match"))?;
+            signatures.push(signature);
+        }
+    }
+
+    (signatures.len() >= threshold as usize)
+        .then_some(())
+        .context(format!(
+            "{} signatures, but threshold is {threshold}",
+            signatures.len()
+        ))?;
+
+    set_multi_signature(unsigned_tx.clone(), &bits, signatures)
+}
+
+/// Placeholder signatures of the first `threshold` members, to simulate a
+/// multisig transaction, as the nodes charge gas for each member signature.
+pub fn placeholder_multi_signature(unsigned_tx: &Tx, public_key: &LegacyAminoPubKey) -> Result<Tx> {
+    let bits = (0..public_key.public_keys.len())
+        .map(|i| i < public_key.threshold as usize)
+        .collect::<Vec<_>>();
+    let signatures = vec![vec![0; 64]; public_key.threshold as usize];
+    set_multi_signature(unsigned_tx.clone(), &bits, signatures)
+}
+
+fn set_multi_signature(mut tx: Tx, bits: &[bool], signatures: Vec<Vec<u8>>) -> Result<Tx> {
+    let single = ModeInfo {
+        sum: Some(Sum::Single(Single {
+            mode: SignMode::LegacyAminoJson.into(),
+        })),
+    };
+
+    let mode_info = ModeInfo {
+        sum: Some(Sum::Multi(Multi {
+            bitarray: Some(compact_bit_array(bits)),
+            mode_infos: vec![single; signatures.len()],
+        })),
+    };
+
+    tx.auth_info
+        .as_mut()
+        .context("no auth_info")?
+        .signer_infos
+        .first_mut()
+        .context("no signer_info")?
+        .mode_info = Some(mode_info);
+    tx.signatures = vec![MultiSignature { signatures }.encode_to_vec()];
+
+    Ok(tx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `wasmd keys add test1`, `test2` and `test3` of the cosmjs fixtures, whose
+    // addresses are wasm1jq59w7y34msq69g4w3zvq6d5h3stcajd8g62xm,
+    // wasm146e52j6zphxw8m67cz8860ad5uju892cqmawsg and wasm1a6uxr25mw8qg8zz3l2avsdjsveh4yg9sw7h5np
+    const MEMBERS: [&str; 3] = [
+        "038cb598ee54130d34f8e0818e7787aa06139a0e2d0026cadb662b55cf16859a67",
+        "02906f1bd9516c8cd3b52639322c801bf8724c1fa5e878c0e32b9bd6c0bb8b0f68",
+        "03cc93519d61b686da6f0e8cff9431e356b45f91063ed6f81f79ddd898858800f3",
+    ];
+
+    fn members() -> Vec<Vec<u8>> {
+        MEMBERS
+            .iter()
+            .map(|key| hex::decode(key).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn sorts_members_by_address() {
+        let mut public_keys = members();
+        public_keys.reverse();
+        sort_public_keys(&mut public_keys);
+        assert_eq!(public_keys, members());
+    }
+
+    #[test]
+    fn encodes_amino_public_key() {
+        // the `tendermint/PubKeyMultisigThreshold` of a 2-of-3 over the members
+        let (hrp, bytes) = bech32::decode(
+            "wasmpub1ytql0csgqgfzd666axrjzquvkkvwu4qnp5603cyp3emc02sxzwdqutgqym9dke3t2h83dpv6vufzd666axrjzq5sdudaj5tv3nfm2f3exgkgqxlcwfxplf0g0rqwx2um6mqthzc0dqfzd666axrjzq7vjdge6cdksmdx7r5vl72rrc6kk30ezp376mup77wamzvgtzqq7v7aysdd",
+        )
+        .unwrap();
+        assert_eq!(hrp.as_str(), "wasmpub");
+        assert_eq!(amino_encode(2, &members()), bytes);
+    }
+
+    #[test]
+    fn derives_address() {
+        assert_eq!(
+            multisig_address(2, &members(), "wasm").unwrap(),
+            "wasm1pzf2wlat97n7rykrk7e8g8nxste6hde0r8jqsy"
+        );
+        assert_eq!(
+            multisig_address(1, &members(), "wasm").unwrap(),
+            "wasm1hez3rmcu96cqv8puvk034yj27vmtgtkzha2ypg"
+        );
+    }
+
+    #[test]
+    fn compacts_bit_array() {
+        let bits = compact_bit_array(&[true, false, true]);
+        assert_eq!(bits.elems, vec![0b1010_0000]);
+        assert_eq!(bits.extra_bits_stored, 3);
+
+        let bits = compact_bit_array(&[false; 8]);
+        assert_eq!(bits.elems, vec![0]);
+        assert_eq!(bits.extra_bits_stored, 0);
+
+        let mut flags = [false; 10];
+        flags[0] = true;
+        flags[9] = true;
+        let bits = compact_bit_array(&flags);
+        assert_eq!(bits.elems, vec![0b1000_0000, 0b0100_0000]);
+        assert_eq!(bits.extra_bits_stored, 2);
+    }
+}
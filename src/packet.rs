@@ -0,0 +1,261 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use futures::StreamExt;
+use tendermint_rpc::query::Query;
+use tendermint_rpc::{Client, Order};
+
+use crate::broadcast::TxResult;
+use crate::query::{get_channel_client_state, has_packet_commitment, has_packet_receipt};
+use crate::Result;
+
+/// Packet of a `send_packet` event.
+#[derive(Debug, Clone)]
+pub struct SendPacket {
+    pub sequence: u64,
+    pub src_port: String,
+    pub src_channel: String,
+    pub dst_port: String,
+    pub dst_channel: String,
+    /// (revision number, revision height), zero without a timeout height
+    pub timeout_height: (u64, u64),
+    /// nanoseconds, zero without a timeout timestamp
+    pub timeout_timestamp: u64,
+}
+
+impl SendPacket {
+    /// Packets sent by a transaction.
+    pub fn from_tx_result(tx_result: &TxResult) -> Result<Vec<Self>> {
+        tx_result
+            .events_of("send_packet")
+            .into_iter()
+            .map(|attributes| {
+                let attribute = |key: &str| {
+                    attributes
+                        .get(key)
+                        .cloned()
+                        .context(format!("send_packet without {key}"))
+                };
+                let timeout_height = attribute("packet_timeout_height")?;
+                let (revision_number, revision_height) = timeout_height
+                    .split_once('-')
+                    .context("invalid packet_timeout_height")?;
+                Ok(Self {
+                    sequence: attribute("packet_sequence")?.parse()?,
+                    src_port: attribute("packet_src_port")?,
+                    src_channel: attribute("packet_src_channel")?,
+                    dst_port: attribute("packet_dst_port")?,
+                    dst_channel: attribute("packet_dst_channel")?,
+                    timeout_height: (revision_number.parse()?, revision_height.parse()?),
+                    timeout_timestamp: attribute("packet_timeout_timestamp")?.parse()?,
+                })
+            })
+            .collect()
+    }
+
+    /// Whether the latest block of the destination chain is past the timeout,
+    /// with the `revision_number` of the destination chain.
+    pub async fn timed_out(&self, destination: &str, revision_number: u64) -> Result<bool> {
+        let rpc_client = tendermint_rpc::HttpClient::new(destination)?;
+        let status = rpc_client.status().await?;
+
+        let height = (
+            revision_number,
+            status.sync_info.latest_block_height.value(),
+        );
+        let timestamp = status.sync_info.latest_block_time.unix_timestamp_nanos();
+
+        Ok(
+            (self.timeout_height != (0, 0) && height >= self.timeout_height)
+                || (self.timeout_timestamp != 0 && timestamp >= self.timeout_timestamp.into()),
+        )
+    }
+
+    /// Acknowledgement written by the destination chain, found by searching
+    /// its transactions, so it needs the tx indexer.
+    pub async fn acknowledgement(&self, destination: &str) -> Result<Option<String>> {
+        let rpc_client = tendermint_rpc::HttpClient::new(destination)?;
+
+        let query = Query::eq(
+            "write_acknowledgement.packet_dst_port",
+            self.dst_port.as_str(),
+        )
+        .and_eq(
+            "write_acknowledgement.packet_dst_channel",
+            self.dst_channel.as_str(),
+        )
+        .and_eq(
+            "write_acknowledgement.packet_sequence",
+            self.sequence.to_string(),
+        );
+
+        let resp = rpc_client
+            .tx_search(query, false, 1, 10, Order::Ascending)
+            .await?;
+
+        for tx in resp.txs {
+            let tx_result = TxResult::try_from(tx)?;
+            let ack = tx_result
+                .events_of("write_acknowledgement")
+                .into_iter()
+                .find(|attributes| {
+                    attributes.get("packet_dst_channel") == Some(&self.dst_channel)
+                        && attributes.get("packet_sequence") == Some(&self.sequence.to_string())
+                })
+                .and_then(|attributes| attributes.get("packet_ack").cloned());
+            if ack.is_some() {
+                return Ok(ack);
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// Error of an acknowledgement, `{"error": "..."}` instead of `{"result": "AQ=="}`.
+pub fn ack_error(ack: &str) -> Option<String> {
+    serde_json::from_str::<serde_json::Value>(ack)
+        .ok()?
+        .get("error")?
+        .as_str()
+        .map(Into::into)
+}
+
+/// Result of the first endpoint which answers.
+async fn first_ok<'a, F, Fut, T>(endpoints: &'a [String], query: F) -> Option<T>
+where
+    F: Fn(&'a str) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    futures::stream::iter(endpoints)
+        .then(|endpoint| query(endpoint))
+        .filter_map(|x| async { x.ok() })
+        .boxed_local()
+        .next()
+        .await
+}
+
+/// Whether the source chain still waits for the acknowledgement or the timeout.
+async fn commitment_pending(source: &[String], packet: &SendPacket) -> Option<bool> {
+    first_ok(source, |endpoint| {
+        has_packet_commitment(
+            endpoint,
+            &packet.src_port,
+            &packet.src_channel,
+            packet.sequence,
+        )
+    })
+    .await
+}
+
+/// Polls the destination chain and the source chain until the packet is
+/// acknowledged or timed out, or the timeout is over.
+pub async fn track_packet(
+    source: &[String],
+    destination: &[String],
+    packet: &SendPacket,
+    timeout: Duration,
+) -> Result<()> {
+    let start = Instant::now();
+    let mut received = false;
+
+    // the client of the source channel tracks the destination chain, at its revision
+    let revision_number = first_ok(source, |endpoint| {
+        get_channel_client_state(endpoint, &packet.src_port, &packet.src_channel)
+    })
+    .await
+    .and_then(|client_state| client_state.latest_height)
+    .map(|height| height.revision_number)
+    .context(format!(
+        "not able to query the client of {}/{}",
+        packet.src_port, packet.src_channel
+    ))?;
+
+    println!(
+        "tracking packet {} over {}/{}",
+        packet.sequence, packet.src_port, packet.src_channel
+    );
+
+    loop {
+        if !received {
+            received = first_ok(destination, |endpoint| {
+                has_packet_receipt(
+                    endpoint,
+                    &packet.dst_port,
+                    &packet.dst_channel,
+                    packet.sequence,
+                )
+            })
+            .await
+            .unwrap_or_default();
+
+            if received {
+                println!(
+                    "packet {} is received over {}/{}",
+                    packet.sequence, packet.dst_port, packet.dst_channel
+                );
+            }
+        }
+
+        if received {
+            let ack = first_ok(destination, |endpoint| packet.acknowledgement(endpoint))
+                .await
+                .flatten();
+
+            if let Some(ack) = ack {
+                let pending = commitment_pending(source, packet).await.unwrap_or(true);
+
+                match ack_error(&ack) {
+                    None => println!("packet {} is acknowledged with success", packet.sequence),
+                    Some(error) => {
+                        println!(
+                            "packet {} is acknowledged with error: {error}",
+                            packet.sequence
+                        );
+                        if pending {
+                            println!("warning: the refund is pending until the acknowledgement is relayed back");
+                        }
+                    }
+                }
+
+                return Ok(());
+            }
+
+            // without the tx indexer, the acknowledgement is only seen once relayed back
+            let relayed = commitment_pending(source, packet)
+                .await
+                .is_some_and(|pending| !pending);
+
+            if relayed {
+                println!("packet {} is acknowledged", packet.sequence);
+                return Ok(());
+            }
+        } else if first_ok(destination, |endpoint| {
+            packet.timed_out(endpoint, revision_number)
+        })
+        .await
+        .unwrap_or_default()
+        {
+            let pending = commitment_pending(source, packet).await.unwrap_or(true);
+
+            println!("packet {} is timed out", packet.sequence);
+            if pending {
+                println!("warning: the refund is pending until the timeout is relayed back");
+            }
+
+            return Ok(());
+        }
+
+        if start.elapsed() >= timeout {
+            return Err(anyhow::anyhow!(
+                "packet {} is not {} in {}s",
+                packet.sequence,
+                if received { "acknowledged" } else { "received" },
+                timeout.as_secs()
+            ));
+        }
+
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    }
+}
@@ -0,0 +1,141 @@
+//! Protobuf types which are not shipped with `cosmos_sdk_proto`.
+//!
+//! These are not registered to the `prost_wkt_types` registry, so they are
+//! packed to and unpacked from `Any`, or queried, by hand.
+
+use cosmos_sdk_proto::cosmos::auth::v1beta1::BaseAccount;
+use cosmos_sdk_proto::cosmos::base::v1beta1::{Coin, DecCoin};
+use cosmos_sdk_proto::ibc::core::client::v1::Height;
+
+// https://github.com/evmos/ethermint/blob/main/proto/ethermint/crypto/v1/ethsecp256k1/keys.proto
+// shares the wire format with `cosmos.crypto.secp256k1.PubKey`
+pub const ETH_SECP256K1_PUBKEY_TYPE_URL: &str = "/ethermint.crypto.v1.ethsecp256k1.PubKey";
+
+// https://github.com/evmos/ethermint/blob/main/proto/ethermint/types/v1/account.proto
+pub const ETH_ACCOUNT_TYPE_URL: &str = "/ethermint.types.v1.EthAccount";
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct EthAccount {
+    #[prost(message, optional, tag = "1")]
+    pub base_account: Option<BaseAccount>,
+    #[prost(string, tag = "2")]
+    pub code_hash: String,
+}
+
+// https://github.com/InjectiveLabs/injective-core/blob/master/proto/injective/crypto/v1beta1/ethsecp256k1/keys.proto
+// shares the wire format with `cosmos.crypto.secp256k1.PubKey`
+pub const INJECTIVE_PUBKEY_TYPE_URL: &str = "/injective.crypto.v1beta1.ethsecp256k1.PubKey";
+
+// https://github.com/InjectiveLabs/injective-core/blob/master/proto/injective/types/v1beta1/account.proto
+pub const INJECTIVE_ETH_ACCOUNT_TYPE_URL: &str = "/injective.types.v1beta1.EthAccount";
+
+/// Injective's `EthAccount`, with the code hash in bytes.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct InjectiveEthAccount {
+    #[prost(message, optional, tag = "1")]
+    pub base_account: Option<BaseAccount>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub code_hash: Vec<u8>,
+}
+
+// https://github.com/skip-mev/feemarket/blob/main/proto/feemarket/feemarket/v1/query.proto
+pub const FEEMARKET_GAS_PRICE_PATH: &str = "/feemarket.feemarket.v1.Query/GasPrice";
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GasPriceRequest {
+    #[prost(string, tag = "1")]
+    pub denom: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GasPriceResponse {
+    #[prost(message, optional, tag = "1")]
+    pub price: Option<DecCoin>,
+}
+
+// https://github.com/osmosis-labs/osmosis/blob/main/proto/osmosis/txfees/v1beta1/query.proto
+pub const OSMOSIS_EIP_BASE_FEE_PATH: &str = "/osmosis.txfees.v1beta1.Query/GetEipBaseFee";
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryEipBaseFeeRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryEipBaseFeeResponse {
+    #[prost(string, tag = "1")]
+    pub base_fee: String,
+}
+
+pub const OSMOSIS_FEE_TOKENS_PATH: &str = "/osmosis.txfees.v1beta1.Query/FeeTokens";
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryFeeTokensRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryFeeTokensResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub fee_tokens: Vec<FeeToken>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FeeToken {
+    #[prost(string, tag = "1")]
+    pub denom: String,
+    #[prost(uint64, tag = "2")]
+    pub pool_id: u64,
+}
+
+// https://github.com/cosmos/gaia/blob/main/proto/gaia/globalfee/v1beta1/query.proto
+pub const GLOBALFEE_MINIMUM_GAS_PRICES_PATH: &str =
+    "/gaia.globalfee.v1beta1.Query/MinimumGasPrices";
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryMinimumGasPricesRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryMinimumGasPricesResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub minimum_gas_prices: Vec<DecCoin>,
+}
+
+// https://github.com/cosmos/ibc-go/blob/main/proto/ibc/applications/transfer/v1/tx.proto
+// the shipped `MsgTransfer` predates the `memo` field of ibc-go v5
+pub const IBC_MSG_TRANSFER_TYPE_URL: &str = "/ibc.applications.transfer.v1.MsgTransfer";
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MsgTransfer {
+    #[prost(string, tag = "1")]
+    pub source_port: String,
+    #[prost(string, tag = "2")]
+    pub source_channel: String,
+    #[prost(message, optional, tag = "3")]
+    pub token: Option<Coin>,
+    #[prost(string, tag = "4")]
+    pub sender: String,
+    #[prost(string, tag = "5")]
+    pub receiver: String,
+    #[prost(message, optional, tag = "6")]
+    pub timeout_height: Option<Height>,
+    #[prost(uint64, tag = "7")]
+    pub timeout_timestamp: u64,
+    #[prost(string, tag = "8")]
+    pub memo: String,
+}
+
+// https://github.com/cosmos/ibc-go/blob/main/proto/ibc/lightclients/tendermint/v1/tendermint.proto
+pub const TENDERMINT_CLIENT_STATE_TYPE_URL: &str = "/ibc.lightclients.tendermint.v1.ClientState";
+
+// https://github.com/cosmos/ibc-go/blob/main/proto/ibc/core/client/v1/query.proto
+// added in ibc-go v2, after the shipped ibc protos
+pub const IBC_CLIENT_STATUS_PATH: &str = "/ibc.core.client.v1.Query/ClientStatus";
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryClientStatusRequest {
+    #[prost(string, tag = "1")]
+    pub client_id: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryClientStatusResponse {
+    #[prost(string, tag = "1")]
+    pub status: String,
+}
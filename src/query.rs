@@ -6,16 +6,41 @@ use cosmos_sdk_proto::cosmos::auth::v1beta1::{
 use cosmos_sdk_proto::cosmos::bank::v1beta1::query_client::QueryClient as QueryTotalSupplyClient;
 use cosmos_sdk_proto::cosmos::bank::v1beta1::{QueryTotalSupplyRequest, QueryTotalSupplyResponse};
 use cosmos_sdk_proto::cosmos::base::query::v1beta1::PageRequest;
+use cosmos_sdk_proto::cosmos::gov;
 use cosmos_sdk_proto::cosmos::vesting::v1beta1::ContinuousVestingAccount;
+use cosmos_sdk_proto::ibc::applications::transfer::v1::{
+    DenomTrace, QueryDenomTracesRequest, QueryDenomTracesResponse,
+};
+use cosmos_sdk_proto::ibc::core::channel::v1::{
+    QueryChannelClientStateRequest, QueryChannelClientStateResponse, QueryChannelsRequest,
+    QueryChannelsResponse, QueryPacketCommitmentRequest, QueryPacketCommitmentResponse,
+    QueryPacketReceiptRequest, QueryPacketReceiptResponse,
+};
+use cosmos_sdk_proto::ibc::core::client::v1::{
+    Height, QueryClientStatesRequest, QueryClientStatesResponse,
+};
+use cosmos_sdk_proto::ibc::core::connection::v1::{
+    QueryConnectionsRequest, QueryConnectionsResponse,
+};
+use cosmos_sdk_proto::ibc::core::{channel, connection};
+use cosmos_sdk_proto::ibc::lightclients::tendermint::v1::ClientState;
 use cosmos_sdk_proto::prost_wkt_types::{Any, MessageSerde};
 use futures::stream::StreamExt;
 use serde_json::Value;
+use sha2::{Digest, Sha256};
 use tendermint::abci::response::Info;
 use tendermint_rpc::endpoint::status::Response as NodeStatus;
 use tendermint_rpc::Client;
 use tracing::info;
 
 use crate::endpoint::get_rpc_endpoints;
+use crate::proto::{
+    GasPriceRequest, GasPriceResponse, QueryClientStatusRequest, QueryClientStatusResponse,
+    QueryEipBaseFeeRequest, QueryEipBaseFeeResponse, QueryFeeTokensRequest, QueryFeeTokensResponse,
+    QueryMinimumGasPricesRequest, QueryMinimumGasPricesResponse, FEEMARKET_GAS_PRICE_PATH,
+    GLOBALFEE_MINIMUM_GAS_PRICES_PATH, IBC_CLIENT_STATUS_PATH, OSMOSIS_EIP_BASE_FEE_PATH,
+    OSMOSIS_FEE_TOKENS_PATH, TENDERMINT_CLIENT_STATE_TYPE_URL,
+};
 use crate::Result;
 
 pub async fn perform_rpc_query<S, R>(endpoint: &str, query: S) -> Result<R>
@@ -40,6 +65,105 @@ where
     crate::utils::read_from_bytes(&resp.value)
 }
 
+/// Queries with the types in [`crate::proto`], which are not in the registry.
+pub async fn perform_raw_rpc_query<S, R>(endpoint: &str, path: &str, query: S) -> Result<R>
+where
+    S: prost::Message,
+    R: prost::Message + Default,
+{
+    try_raw_rpc_query(endpoint, path, query)
+        .await?
+        .map_err(|log| anyhow::anyhow!("{path} failed: {log}"))
+}
+
+/// Like [`perform_raw_rpc_query`], but a query the node fails is returned as
+/// its log, so that it can be told apart from an unreachable endpoint.
+async fn try_raw_rpc_query<S, R>(
+    endpoint: &str,
+    path: &str,
+    query: S,
+) -> Result<std::result::Result<R, String>>
+where
+    S: prost::Message,
+    R: prost::Message + Default,
+{
+    let rpc_client = tendermint_rpc::HttpClient::new(endpoint)?;
+
+    let resp = rpc_client
+        .abci_query(Some(path.into()), query.encode_to_vec(), None, false)
+        .await?;
+
+    if resp.code.is_err() {
+        return Ok(Err(resp.log));
+    }
+
+    Ok(Ok(R::decode(resp.value.as_slice())?))
+}
+
+/// `LegacyDec` is encoded as its integer value with 18 decimals, unless it is
+/// already a decimal string.
+fn parse_proto_dec(dec: &str) -> Result<f64> {
+    Ok(if dec.contains('.') {
+        dec.parse()?
+    } else {
+        dec.parse::<f64>()? / 1e18
+    })
+}
+
+/// Current base gas price of x/feemarket, or of Osmosis' EIP-1559 txfees module.
+pub async fn get_base_gas_price(endpoint: &str, denom: &str) -> Result<f64> {
+    let feemarket: Result<GasPriceResponse> = perform_raw_rpc_query(
+        endpoint,
+        FEEMARKET_GAS_PRICE_PATH,
+        GasPriceRequest {
+            denom: denom.into(),
+        },
+    )
+    .await;
+
+    match feemarket {
+        Ok(resp) => parse_proto_dec(&resp.price.context("no price")?.amount),
+        Err(_) => {
+            let resp: QueryEipBaseFeeResponse = perform_raw_rpc_query(
+                endpoint,
+                OSMOSIS_EIP_BASE_FEE_PATH,
+                QueryEipBaseFeeRequest {},
+            )
+            .await?;
+            parse_proto_dec(&resp.base_fee)
+        }
+    }
+}
+
+/// Fee denoms accepted by the globalfee or Osmosis' txfees module, with their
+/// minimum gas prices when the module has them.
+pub async fn get_accepted_fee_denoms(endpoint: &str) -> Result<Vec<(String, Option<f64>)>> {
+    let globalfee: Result<QueryMinimumGasPricesResponse> = perform_raw_rpc_query(
+        endpoint,
+        GLOBALFEE_MINIMUM_GAS_PRICES_PATH,
+        QueryMinimumGasPricesRequest {},
+    )
+    .await;
+
+    match globalfee {
+        Ok(resp) => resp
+            .minimum_gas_prices
+            .into_iter()
+            .map(|price| Ok((price.denom, Some(parse_proto_dec(&price.amount)?))))
+            .collect(),
+        Err(_) => {
+            let resp: QueryFeeTokensResponse =
+                perform_raw_rpc_query(endpoint, OSMOSIS_FEE_TOKENS_PATH, QueryFeeTokensRequest {})
+                    .await?;
+            Ok(resp
+                .fee_tokens
+                .into_iter()
+                .map(|fee_token| (fee_token.denom, None))
+                .collect())
+        }
+    }
+}
+
 pub async fn get_account_info(endpoint: &str, address: &str) -> Result<QueryAccountResponse> {
     let query = QueryAccountRequest {
         address: address.into(),
@@ -66,6 +190,366 @@ pub async fn validate_rpc(endpoint: &str, chain_id: &str) -> Result<u64> {
         ))
 }
 
+/// Whether the chain serves `cosmos.gov.v1`, which came with SDK 0.46.
+pub async fn supports_gov_v1(endpoint: &str) -> Result<bool> {
+    Ok(try_raw_rpc_query::<_, gov::v1::QueryParamsResponse>(
+        endpoint,
+        "/cosmos.gov.v1.Query/Params",
+        gov::v1::QueryParamsRequest {
+            params_type: "voting".into(),
+        },
+    )
+    .await?
+    .is_ok())
+}
+
+/// Ids of the proposals in voting period.
+pub async fn get_voting_proposals(endpoint: &str, gov_v1: bool) -> Result<Vec<u64>> {
+    let pagination = Some(PageRequest {
+        limit: u64::MAX,
+        ..Default::default()
+    });
+
+    Ok(if gov_v1 {
+        perform_raw_rpc_query::<_, gov::v1::QueryProposalsResponse>(
+            endpoint,
+            "/cosmos.gov.v1.Query/Proposals",
+            gov::v1::QueryProposalsRequest {
+                proposal_status: gov::v1::ProposalStatus::VotingPeriod.into(),
+                pagination,
+                ..Default::default()
+            },
+        )
+        .await?
+        .proposals
+        .into_iter()
+        .map(|proposal| proposal.id)
+        .collect()
+    } else {
+        perform_raw_rpc_query::<_, gov::v1beta1::QueryProposalsResponse>(
+            endpoint,
+            "/cosmos.gov.v1beta1.Query/Proposals",
+            gov::v1beta1::QueryProposalsRequest {
+                proposal_status: gov::v1beta1::ProposalStatus::VotingPeriod.into(),
+                pagination,
+                ..Default::default()
+            },
+        )
+        .await?
+        .proposals
+        .into_iter()
+        .map(|proposal| proposal.proposal_id)
+        .collect()
+    })
+}
+
+/// Whether `voter` has voted on the proposal. Nodes answer with the gov
+/// `voter: .. not found for proposal: ..` error if not.
+pub async fn has_voted(
+    endpoint: &str,
+    gov_v1: bool,
+    proposal_id: u64,
+    voter: &str,
+) -> Result<bool> {
+    let voted = if gov_v1 {
+        try_raw_rpc_query::<_, gov::v1::QueryVoteResponse>(
+            endpoint,
+            "/cosmos.gov.v1.Query/Vote",
+            gov::v1::QueryVoteRequest {
+                proposal_id,
+                voter: voter.into(),
+            },
+        )
+        .await?
+        .map(|resp| resp.vote.is_some())
+    } else {
+        try_raw_rpc_query::<_, gov::v1beta1::QueryVoteResponse>(
+            endpoint,
+            "/cosmos.gov.v1beta1.Query/Vote",
+            gov::v1beta1::QueryVoteRequest {
+                proposal_id,
+                voter: voter.into(),
+            },
+        )
+        .await?
+        .map(|resp| resp.vote.is_some())
+    };
+
+    match voted {
+        Ok(voted) => Ok(voted),
+        Err(log) if log.contains("not found for proposal") => Ok(false),
+        Err(log) => Err(anyhow::anyhow!(
+            "not able to query the vote on proposal {proposal_id}: {log}"
+        )),
+    }
+}
+
+/// Denom traces of the IBC vouchers on the chain.
+pub async fn get_denom_traces(endpoint: &str) -> Result<Vec<DenomTrace>> {
+    Ok(perform_raw_rpc_query::<_, QueryDenomTracesResponse>(
+        endpoint,
+        "/ibc.applications.transfer.v1.Query/DenomTraces",
+        QueryDenomTracesRequest {
+            pagination: Some(PageRequest {
+                limit: u64::MAX,
+                ..Default::default()
+            }),
+        },
+    )
+    .await?
+    .denom_traces)
+}
+
+/// Resolves a human denom to the `ibc/HASH` voucher denom to send from the chain.
+///
+/// `ibc/HASH` and the chain denom, `native_denom`, are kept. A full trace, e.g.
+/// `transfer/channel-0/uatom`, is hashed. Another base denom is looked up in the
+/// denom traces; if it arrived over more than one channel, the trace over
+/// `channel` is preferred.
+pub async fn resolve_ibc_denom(
+    endpoint: &str,
+    denom: &str,
+    native_denom: &str,
+    channel: &str,
+) -> Result<String> {
+    if denom.starts_with("ibc/") || denom == native_denom {
+        return Ok(denom.into());
+    }
+
+    if denom.starts_with("transfer/") {
+        return Ok(ibc_denom(denom));
+    }
+
+    let traces = get_denom_traces(endpoint)
+        .await?
+        .into_iter()
+        .filter(|trace| trace.base_denom == denom)
+        .collect::<Vec<_>>();
+
+    let trace = match traces.as_slice() {
+        [] => return Ok(denom.into()),
+        [trace] => trace,
+        _ => traces
+            .iter()
+            .find(|trace| trace.path == format!("transfer/{channel}"))
+            .context(format!(
+                "{denom} is ambiguous, use one of the traces: {}",
+                traces
+                    .iter()
+                    .map(|trace| format!("{}/{}", trace.path, trace.base_denom))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))?,
+    };
+
+    Ok(ibc_denom(&format!("{}/{}", trace.path, trace.base_denom)))
+}
+
+/// `ibc/` followed by the uppercase hex of the SHA256 of the full denom trace.
+pub fn ibc_denom(trace: &str) -> String {
+    format!(
+        "ibc/{}",
+        hex::encode_upper(Sha256::digest(trace.as_bytes()))
+    )
+}
+
+/// Tendermint client state of the channel, which tracks the counterparty chain.
+pub async fn get_channel_client_state(
+    endpoint: &str,
+    port: &str,
+    channel: &str,
+) -> Result<ClientState> {
+    let client_state = perform_raw_rpc_query::<_, QueryChannelClientStateResponse>(
+        endpoint,
+        "/ibc.core.channel.v1.Query/ChannelClientState",
+        QueryChannelClientStateRequest {
+            port_id: port.into(),
+            channel_id: channel.into(),
+        },
+    )
+    .await?
+    .identified_client_state
+    .and_then(|identified| identified.client_state)
+    .context(format!("no client state for {port}/{channel}"))?;
+
+    (client_state.type_url == TENDERMINT_CLIENT_STATE_TYPE_URL)
+        .then_some(())
+        .context(format!("{} is not supported", client_state.type_url))?;
+
+    Ok(<ClientState as prost::Message>::decode(
+        client_state.value.as_slice(),
+    )?)
+}
+
+/// Latest height of the counterparty chain, as tracked by the client of the channel.
+pub async fn get_counterparty_height(endpoint: &str, port: &str, channel: &str) -> Result<Height> {
+    get_channel_client_state(endpoint, port, channel)
+        .await?
+        .latest_height
+        .context("client state without latest height")
+}
+
+/// Whether the chain has received the packet, on an unordered channel.
+pub async fn has_packet_receipt(
+    endpoint: &str,
+    port: &str,
+    channel: &str,
+    sequence: u64,
+) -> Result<bool> {
+    Ok(perform_raw_rpc_query::<_, QueryPacketReceiptResponse>(
+        endpoint,
+        "/ibc.core.channel.v1.Query/PacketReceipt",
+        QueryPacketReceiptRequest {
+            port_id: port.into(),
+            channel_id: channel.into(),
+            sequence,
+        },
+    )
+    .await?
+    .received)
+}
+
+/// Whether the chain still has the commitment of a sent packet, which is
+/// deleted once it is acknowledged or timed out on the chain.
+pub async fn has_packet_commitment(
+    endpoint: &str,
+    port: &str,
+    channel: &str,
+    sequence: u64,
+) -> Result<bool> {
+    let rpc_client = tendermint_rpc::HttpClient::new(endpoint)?;
+
+    let resp = rpc_client
+        .abci_query(
+            Some("/ibc.core.channel.v1.Query/PacketCommitment".into()),
+            prost::Message::encode_to_vec(&QueryPacketCommitmentRequest {
+                port_id: port.into(),
+                channel_id: channel.into(),
+                sequence,
+            }),
+            None,
+            false,
+        )
+        .await?;
+
+    // a missing commitment is answered with an error
+    Ok(resp.code.is_ok()
+        && !<QueryPacketCommitmentResponse as prost::Message>::decode(resp.value.as_slice())?
+            .commitment
+            .is_empty())
+}
+
+/// Open transfer channel of the chain to the counterparty chain, over an
+/// active client which tracks it. If there are several, the oldest is used.
+pub async fn find_transfer_channel(endpoint: &str, counterparty_chain_id: &str) -> Result<String> {
+    let pagination = Some(PageRequest {
+        limit: u64::MAX,
+        ..Default::default()
+    });
+
+    let client_ids = perform_raw_rpc_query::<_, QueryClientStatesResponse>(
+        endpoint,
+        "/ibc.core.client.v1.Query/ClientStates",
+        QueryClientStatesRequest {
+            pagination: pagination.clone(),
+        },
+    )
+    .await?
+    .client_states
+    .into_iter()
+    .filter(|identified| {
+        identified
+            .client_state
+            .as_ref()
+            .is_some_and(|client_state| {
+                client_state.type_url == TENDERMINT_CLIENT_STATE_TYPE_URL
+                    && <ClientState as prost::Message>::decode(client_state.value.as_slice())
+                        .is_ok_and(|client_state| client_state.chain_id == counterparty_chain_id)
+            })
+    })
+    .map(|identified| identified.client_id)
+    .collect::<Vec<_>>();
+
+    // expired and frozen clients can't relay packets
+    let mut active_client_ids = vec![];
+    for client_id in client_ids {
+        let status = perform_raw_rpc_query::<_, QueryClientStatusResponse>(
+            endpoint,
+            IBC_CLIENT_STATUS_PATH,
+            QueryClientStatusRequest {
+                client_id: client_id.clone(),
+            },
+        )
+        .await;
+        // chains before ibc-go v2 don't serve the client status
+        if status.map(|resp| resp.status == "Active").unwrap_or(true) {
+            active_client_ids.push(client_id);
+        }
+    }
+
+    let connection_ids = perform_raw_rpc_query::<_, QueryConnectionsResponse>(
+        endpoint,
+        "/ibc.core.connection.v1.Query/Connections",
+        QueryConnectionsRequest {
+            pagination: pagination.clone(),
+        },
+    )
+    .await?
+    .connections
+    .into_iter()
+    .filter(|connection| {
+        connection.state == connection::v1::State::Open as i32
+            && active_client_ids.contains(&connection.client_id)
+    })
+    .map(|connection| connection.id)
+    .collect::<Vec<_>>();
+
+    let mut channel_ids = perform_raw_rpc_query::<_, QueryChannelsResponse>(
+        endpoint,
+        "/ibc.core.channel.v1.Query/Channels",
+        QueryChannelsRequest { pagination },
+    )
+    .await?
+    .channels
+    .into_iter()
+    .filter(|channel| {
+        channel.port_id == "transfer"
+            && channel.state == channel::v1::State::Open as i32
+            && channel
+                .connection_hops
+                .first()
+                .is_some_and(|connection_id| connection_ids.contains(connection_id))
+    })
+    .map(|channel| channel.channel_id)
+    .collect::<Vec<_>>();
+
+    channel_ids.sort_by_key(|channel_id| {
+        channel_id
+            .trim_start_matches("channel-")
+            .parse::<u64>()
+            .unwrap_or(u64::MAX)
+    });
+
+    let (channel_id, others) = channel_ids.split_first().context(format!(
+        "no open transfer channel to {counterparty_chain_id}"
+    ))?;
+
+    if !others.is_empty() {
+        println!(
+            "warning: using {channel_id}, {} are open to {counterparty_chain_id} too",
+            others.join(", ")
+        );
+    }
+
+    Ok(channel_id.clone())
+}
+
+pub async fn get_latest_height_rpc(endpoint: &str) -> Result<u64> {
+    let rpc_client = tendermint_rpc::HttpClient::new(endpoint)?;
+    let resp = rpc_client.status().await?;
+    Ok(resp.sync_info.latest_block_height.value())
+}
+
 pub async fn get_chain_id_rpc(endpoint: &str) -> Result<String> {
     let rpc_client = tendermint_rpc::HttpClient::new(endpoint)?;
     let resp = rpc_client.status().await?;
@@ -93,6 +577,15 @@ pub async fn get_node_info_rpc(endpoint: &str) -> Result<(NodeStatus, Info)> {
 }
 
 pub async fn get_balance(address: &str, endpoint: &str) -> Result<Vec<(String, u128)>> {
+    Ok(get_all_balances(address, endpoint)
+        .await?
+        .into_iter()
+        .filter(|(denom, _)| !denom.contains('/'))
+        .collect())
+}
+
+/// Balances, including IBC and factory denoms.
+pub async fn get_all_balances(address: &str, endpoint: &str) -> Result<Vec<(String, u128)>> {
     let q = cosmos_sdk_proto::cosmos::bank::v1beta1::QueryAllBalancesRequest {
         address: address.into(),
         ..Default::default()
@@ -105,7 +598,6 @@ pub async fn get_balance(address: &str, endpoint: &str) -> Result<Vec<(String, u
 
     resp.balances
         .into_iter()
-        .filter(|c| !c.denom.contains('/'))
         .map(|c| Ok((c.denom, c.amount.parse()?)))
         .collect::<Result<Vec<_>>>()
 }
@@ -132,6 +624,30 @@ pub async fn get_delegated(address: &str, endpoint: &str) -> Result<Vec<(String,
         .collect::<Result<Vec<_>>>()
 }
 
+/// Unbonding entries from a validator, with their creation heights and balances.
+pub async fn get_unbonding(
+    address: &str,
+    validator: &str,
+    endpoint: &str,
+) -> Result<Vec<(i64, u128)>> {
+    let q = cosmos_sdk_proto::cosmos::staking::v1beta1::QueryUnbondingDelegationRequest {
+        delegator_addr: address.into(),
+        validator_addr: validator.into(),
+    };
+
+    let resp: cosmos_sdk_proto::cosmos::staking::v1beta1::QueryUnbondingDelegationResponse =
+        perform_rpc_query(endpoint, q).await?;
+
+    info!("[Unbonding] {:?}", resp);
+
+    resp.unbond
+        .context("no unbonding delegation")?
+        .entries
+        .into_iter()
+        .map(|entry| Ok((entry.creation_height, entry.balance.parse()?)))
+        .collect::<Result<Vec<_>>>()
+}
+
 pub async fn get_rewards(address: &str, endpoint: &str) -> Result<Vec<(String, u128)>> {
     let q = cosmos_sdk_proto::cosmos::distribution::v1beta1::QueryDelegationTotalRewardsRequest {
         delegator_address: address.into(),
@@ -211,6 +727,7 @@ pub async fn get_rpc_endpoint_chain_info(rpc_endpoint: &str) -> Result<crate::ch
             prefix: prefix.to_string(),
             fee: 0,
             denom: denoms[0].into(),
+            ..Default::default()
         }
     } else {
         let question = requestty::Question::select(&format!("choose denom for {chain_id}"))
@@ -229,6 +746,25 @@ pub async fn get_rpc_endpoint_chain_info(rpc_endpoint: &str) -> Result<crate::ch
             prefix: prefix.to_string(),
             fee: 0,
             denom,
+            ..Default::default()
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ibc_denom_hashes_trace() {
+        // ATOM on Osmosis, and OSMO on the Cosmos Hub
+        assert_eq!(
+            ibc_denom("transfer/channel-0/uatom"),
+            "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2"
+        );
+        assert_eq!(
+            ibc_denom("transfer/channel-141/uosmo"),
+            "ibc/14F9BC3E44B8A9C1BE1FB08980FAB87034C9905EF17CF2F5008FC085218811CC"
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// `ErrWrongSequence` of the sdk error codespace.
+pub const SEQUENCE_MISMATCH_CODE: u32 = 32;
+/// Codespace of the errors registered by the sdk itself.
+pub const SDK_CODESPACE: &str = "sdk";
+
+lazy_static::lazy_static! {
+    static ref SEQUENCE_TRACKER: SequenceTracker = SequenceTracker::default();
+}
+
+/// Sequences of the transactions accepted by `CheckTx`, per (chain id, address).
+///
+/// Nodes only update the account sequence after a block, so transactions sent
+/// back-to-back from one account need the next sequence from here. The
+/// sequences live only as long as the process; a new one starts from the
+/// on-chain sequence.
+#[derive(Debug, Default, Clone)]
+pub struct SequenceTracker {
+    sequences: Arc<Mutex<HashMap<(String, String), u64>>>,
+}
+
+impl SequenceTracker {
+    /// Tracker shared by the whole process.
+    pub fn global() -> &'static Self {
+        &SEQUENCE_TRACKER
+    }
+
+    pub fn get(&self, chain_id: &str, address: &str) -> Option<u64> {
+        self.sequences
+            .lock()
+            .expect("poisoned lock")
+            .get(&(chain_id.to_string(), address.to_string()))
+            .copied()
+    }
+
+    pub fn set(&self, chain_id: &str, address: &str, sequence: u64) {
+        self.sequences
+            .lock()
+            .expect("poisoned lock")
+            .insert((chain_id.to_string(), address.to_string()), sequence);
+    }
+
+    /// Forgets the tracked sequence, for one the chain may not have accepted.
+    pub fn reset(&self, chain_id: &str, address: &str) {
+        self.sequences
+            .lock()
+            .expect("poisoned lock")
+            .remove(&(chain_id.to_string(), address.to_string()));
+    }
+
+    /// Sequence to sign with, given the one queried from the chain.
+    pub fn next(&self, chain_id: &str, address: &str, on_chain: u64) -> u64 {
+        self.get(chain_id, address)
+            .map_or(on_chain, |sequence| sequence.max(on_chain))
+    }
+
+    /// Records a transaction with `sequence` accepted by `CheckTx`.
+    pub fn accepted(&self, chain_id: &str, address: &str, sequence: u64) {
+        self.set(chain_id, address, sequence + 1);
+    }
+}
+
+/// Parses the expected sequence out of an `account sequence mismatch` log,
+/// e.g. `account sequence mismatch, expected 5, got 4: incorrect account sequence`.
+pub fn expected_sequence(raw_log: &str) -> Option<u64> {
+    regex::Regex::new(r"expected (\d+), got \d+")
+        .ok()?
+        .captures(raw_log)?
+        .get(1)?
+        .as_str()
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_accepted_sequences() {
+        let tracker = SequenceTracker::default();
+        assert_eq!(tracker.next("chain-1", "addr", 5), 5);
+        tracker.accepted("chain-1", "addr", 5);
+        assert_eq!(tracker.next("chain-1", "addr", 5), 6);
+        assert_eq!(tracker.next("chain-1", "addr", 9), 9);
+        assert_eq!(tracker.next("chain-2", "addr", 5), 5);
+        tracker.reset("chain-1", "addr");
+        assert_eq!(tracker.next("chain-1", "addr", 5), 5);
+    }
+
+    #[test]
+    fn reads_expected_sequence() {
+        let raw_log = "account sequence mismatch, expected 42, got 41: incorrect account sequence";
+        assert_eq!(expected_sequence(raw_log), Some(42));
+    }
+
+    #[test]
+    fn ignores_other_logs() {
+        assert_eq!(
+            expected_sequence("insufficient fees; got: 10uatom required: 20uatom"),
+            None
+        );
+        assert_eq!(expected_sequence(""), None);
+    }
+}
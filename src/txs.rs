@@ -1,6 +1,7 @@
 use anyhow::Context;
+use base64::prelude::{Engine as _, BASE64_STANDARD};
 use bip32::secp256k1::ecdsa::signature::{SignatureEncoding, Signer, Verifier};
-use bip32::secp256k1::ecdsa::Signature;
+use bip32::secp256k1::ecdsa::{Signature, VerifyingKey};
 use cosmos_sdk_proto::cosmos::auth::v1beta1::BaseAccount;
 use cosmos_sdk_proto::cosmos::base::v1beta1::Coin;
 use cosmos_sdk_proto::cosmos::crypto::secp256k1::PubKey;
@@ -11,12 +12,57 @@ use cosmos_sdk_proto::cosmos::tx::v1beta1::{
 };
 use cosmos_sdk_proto::cosmos::vesting::v1beta1::ContinuousVestingAccount;
 use cosmos_sdk_proto::prost_wkt_types::{Any, MessageSerde};
+use prost::Message;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::proto::{
+    EthAccount, InjectiveEthAccount, MsgTransfer, ETH_ACCOUNT_TYPE_URL, IBC_MSG_TRANSFER_TYPE_URL,
+    INJECTIVE_ETH_ACCOUNT_TYPE_URL,
+};
 use crate::Result;
 
+/// Transaction, along with the data needed to sign it elsewhere.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TxFile {
+    pub chain_id: String,
+    pub account_number: u64,
+    pub sequence: u64,
+    pub tx: Tx,
+}
+
+impl TxFile {
+    pub fn new(chain_id: &str, account_number: u64, tx: Tx) -> Result<Self> {
+        Ok(Self {
+            chain_id: chain_id.into(),
+            account_number,
+            sequence: get_sequence(&tx)?,
+            tx,
+        })
+    }
+}
+
+/// Body options and the initial gas limit of a transaction.
+#[derive(Debug, Clone)]
+pub struct TxOptions {
+    pub memo: String,
+    pub timeout_height: u64,
+    pub gas_limit: u64,
+}
+
+impl Default for TxOptions {
+    fn default() -> Self {
+        Self {
+            memo: String::new(),
+            timeout_height: 0,
+            gas_limit: 400_000,
+        }
+    }
+}
+
+/// The public key may be missing, for `sign` to add it offline.
 pub fn generate_auth_info(
-    public_key: PubKey,
+    public_key: Option<Any>,
     sequence: u64,
     gas: u64,
     fee_amount: &u128,
@@ -29,7 +75,7 @@ pub fn generate_auth_info(
     };
 
     let signer_info = SignerInfo {
-        public_key: Some(Any::try_pack(public_key)?),
+        public_key,
         mode_info: Some(mode_info),
         sequence,
     };
@@ -85,7 +131,7 @@ where
     Ok(signature.to_vec().try_into().expect("64 sized vector"))
 }
 
-pub fn update_signature(mut tx: Tx, signature: &[u8; 64]) -> Tx {
+pub fn update_signature(mut tx: Tx, signature: &[u8]) -> Tx {
     tx.signatures = vec![signature.to_vec()];
     tx
 }
@@ -112,27 +158,121 @@ pub fn verify_transaction<K>(
 where
     K: Verifier<Signature>,
 {
-    let sign_doc = crate::txs::generate_sign_doc(signed_tx, chain_id, account_number)?;
-    let signature = signed_tx.signatures[0].clone();
+    let signer_info = signed_tx
+        .auth_info
+        .as_ref()
+        .context("no auth_info")?
+        .signer_infos
+        .first()
+        .context("no signer_info")?;
+    let sign_bytes = match signer_info.mode_info.as_ref().and_then(|x| x.sum.as_ref()) {
+        Some(Sum::Single(Single { mode })) if *mode == i32::from(SignMode::LegacyAminoJson) => {
+            serde_json::to_vec(&generate_legacy_amino_json(
+                signed_tx,
+                chain_id,
+                account_number,
+            )?)?
+        }
+        _ => generate_sign_doc(signed_tx, chain_id, account_number)?.try_encoded()?,
+    };
+    let signature = signed_tx.signatures.first().context("no signature")?;
     pub_key
         .verify(
-            &sign_doc.try_encoded()?,
-            &Signature::from_slice(&signature).expect("error"),
+            &sign_bytes,
+            &Signature::from_slice(signature)
+                .map_err(|e| anyhow::anyhow!("invalid signature: {e}"))?,
         )
-        .expect("error");
+        .map_err(|_| anyhow::anyhow!("signature does not match"))?;
     Ok(())
 }
 
+/// Secp256k1 key of the first signer, to verify the transaction with.
+pub fn signer_public_key(tx: &Tx) -> Result<VerifyingKey> {
+    let public_key = tx
+        .auth_info
+        .as_ref()
+        .context("no auth_info")?
+        .signer_infos
+        .first()
+        .context("no signer_info")?
+        .public_key
+        .as_ref()
+        .context("no public key")?;
+    (public_key.type_url == "/cosmos.crypto.secp256k1.PubKey")
+        .then_some(())
+        .context(format!("can not verify {} keys", public_key.type_url))?;
+    VerifyingKey::from_sec1_bytes(&PubKey::decode(public_key.value.as_slice())?.key)
+        .map_err(|e| anyhow::anyhow!("invalid public key: {e}"))
+}
+
+fn any_to_json(any: &Any) -> Value {
+    serde_json::to_value(any).unwrap_or_else(|_| {
+        serde_json::json!({
+            "@type": any.type_url,
+            "value": BASE64_STANDARD.encode(&any.value),
+        })
+    })
+}
+
+/// Human readable view of a transaction, with its messages unpacked.
+pub fn inspect_transaction(tx: &Tx) -> Result<Value> {
+    let body = tx.body.as_ref().context("no body")?;
+    let auth_info = tx.auth_info.as_ref().context("no auth_info")?;
+    let fee = auth_info.fee.as_ref().context("no fee")?;
+
+    let messages: Vec<Value> = body.messages.iter().map(any_to_json).collect();
+    let signer_infos: Vec<Value> = auth_info
+        .signer_infos
+        .iter()
+        .map(|signer_info| {
+            serde_json::json!({
+                "public_key": signer_info.public_key.as_ref().map(any_to_json),
+                "mode_info": signer_info.mode_info,
+                "sequence": signer_info.sequence.to_string(),
+            })
+        })
+        .collect();
+    let signatures: Vec<String> = tx
+        .signatures
+        .iter()
+        .map(|x| BASE64_STANDARD.encode(x))
+        .collect();
+
+    Ok(serde_json::json!({
+        "messages": messages,
+        "memo": body.memo,
+        "timeout_height": body.timeout_height.to_string(),
+        "fee": {
+            "amount": fee.amount,
+            "gas_limit": fee.gas_limit.to_string(),
+            "payer": fee.payer,
+            "granter": fee.granter,
+        },
+        "signer_infos": signer_infos,
+        "signatures": signatures,
+    }))
+}
+
 fn is_registered(msg_type: &str) -> bool {
     // RegisterLegacyAminoCodec
     // bank, staking, distribution, feegrant, authz, gov
     // TODO: add other msg paths
     [
         "/cosmos.bank.v1beta1.MsgSend",
+        "/cosmos.bank.v1beta1.MsgMultiSend",
         "/cosmos.staking.v1beta1.MsgDelegate",
         "/cosmos.staking.v1beta1.MsgBeginRedelegate",
+        "/cosmos.staking.v1beta1.MsgUndelegate",
+        "/cosmos.staking.v1beta1.MsgCancelUnbondingDelegation",
         "/cosmos.distribution.v1beta1.MsgWithdrawDelegatorReward",
+        "/cosmos.distribution.v1beta1.MsgSetWithdrawAddress",
         "/cosmos.gov.v1beta1.MsgVote",
+        "/cosmos.gov.v1beta1.MsgVoteWeighted",
+        "/cosmos.gov.v1beta1.MsgDeposit",
+        "/cosmos.gov.v1.MsgVote",
+        "/cosmos.gov.v1.MsgVoteWeighted",
+        "/cosmos.gov.v1.MsgDeposit",
+        "/cosmos.gov.v1.MsgSubmitProposal",
         "/ibc.applications.transfer.v1.MsgTransfer",
     ]
     .into_iter()
@@ -145,15 +285,48 @@ fn amino_json(mut msg: Value) -> Result<Value> {
     let (_, msg_type) = map.remove_entry("@type").context("no entry")?;
     let msg_type = msg_type.as_str().context("not str")?;
 
+    // the messages of a v1 proposal are in their amino JSON too
+    let messages = match map.remove("messages") {
+        Some(Value::Array(messages)) => Some(
+            messages
+                .into_iter()
+                .map(amino_json)
+                .collect::<Result<Vec<_>>>()?,
+        ),
+        _ => None,
+    };
+
     remove_type(&mut msg);
 
+    let map = msg.as_object_mut().context("not object")?;
+    if let Some(messages) = messages {
+        map.insert("messages".into(), Value::Array(messages));
+    }
+    // v1beta1 weights are `LegacyDec` integers in proto JSON, decimals in amino JSON
+    if msg_type == "/cosmos.gov.v1beta1.MsgVoteWeighted" {
+        for option in map
+            .get_mut("options")
+            .and_then(Value::as_array_mut)
+            .context("no options")?
+        {
+            let weight = option.get_mut("weight").context("no weight")?;
+            let dec = weight.as_str().context("not str")?.parse()?;
+            *weight = Value::String(crate::utils::format_legacy_dec(dec));
+        }
+    }
+
     if is_registered(msg_type) {
         let split_msg_type = msg_type.rsplit_once('.').context(". not split")?.1;
         let split_msg_type = match split_msg_type {
             "MsgWithdrawDelegatorReward" => "MsgWithdrawDelegationReward",
+            "MsgSetWithdrawAddress" => "MsgModifyWithdrawAddress",
             _ => split_msg_type,
         };
-        let msg_type = format!("cosmos-sdk/{split_msg_type}");
+        let msg_type = if msg_type.starts_with("/cosmos.gov.v1.") {
+            format!("cosmos-sdk/v1/{split_msg_type}")
+        } else {
+            format!("cosmos-sdk/{split_msg_type}")
+        };
         Ok(serde_json::json!({
             "type": msg_type,
             "value": msg,
@@ -173,6 +346,10 @@ fn remove_type(msg: &mut Value) {
                 if k.ends_with("_id") {
                     *v = Value::String(v.to_string())
                 }
+                // `creation_height`, but not the `Height` of `timeout_height`
+                if k.ends_with("_height") && v.is_number() {
+                    *v = Value::String(v.to_string())
+                }
                 if k.ends_with("_timestamp") {
                     *v = Value::String(v.to_string())
                 }
@@ -185,6 +362,39 @@ fn remove_type(msg: &mut Value) {
     }
 }
 
+/// The registry decodes `MsgTransfer` without its `memo`, which must be signed too.
+fn ibc_transfer_json(msg: &Any) -> Result<Value> {
+    let msg = MsgTransfer::decode(msg.value.as_slice())?;
+
+    let mut value = serde_json::json!({
+        "@type": IBC_MSG_TRANSFER_TYPE_URL,
+        "source_port": msg.source_port,
+        "source_channel": msg.source_channel,
+        "token": msg.token,
+        "sender": msg.sender,
+        "receiver": msg.receiver,
+        "timeout_height": {},
+    });
+
+    // amino omits the empty fields
+    if msg.timeout_timestamp > 0 {
+        value["timeout_timestamp"] = msg.timeout_timestamp.to_string().into();
+    }
+    if let Some(height) = msg.timeout_height {
+        if height.revision_number > 0 {
+            value["timeout_height"]["revision_number"] = height.revision_number.to_string().into();
+        }
+        if height.revision_height > 0 {
+            value["timeout_height"]["revision_height"] = height.revision_height.to_string().into();
+        }
+    }
+    if !msg.memo.is_empty() {
+        value["memo"] = msg.memo.into();
+    }
+
+    Ok(value)
+}
+
 pub fn generate_legacy_amino_json(
     tx: &Tx,
     chain_id: &str,
@@ -207,32 +417,41 @@ pub fn generate_legacy_amino_json(
         .as_ref()
         .context("no fee")?
         .amount;
-    let msgs: Result<Vec<_>> = tx
-        .body
-        .as_ref()
-        .context("no body")?
+    let body = tx.body.as_ref().context("no body")?;
+    let msgs: Result<Vec<_>> = body
         .messages
         .iter()
-        .map(|x| serde_json::to_value(x).expect("error"))
-        .map(amino_json)
+        .map(|x| {
+            if x.type_url == IBC_MSG_TRANSFER_TYPE_URL {
+                ibc_transfer_json(x)
+            } else {
+                Ok(serde_json::to_value(x).expect("error"))
+            }
+        })
+        .map(|x| x.and_then(amino_json))
         .collect();
 
     // https://github.com/cosmos/ledger-cosmos/blob/main/docs/TXSPEC.md
     // it does not mention, but the key-values must be string
     // `account_number: "23"` instead of `account_number: 23`
 
-    let payload = serde_json::json!({
+    let mut payload = serde_json::json!({
       "account_number": account_number.to_string(),
       "chain_id": chain_id.to_string(),
       "fee": {
         "amount": fee_amount,
         "gas": gas.to_string()
       },
-      "memo": "",
+      "memo": body.memo,
       "msgs": msgs?,
       "sequence": sequence.to_string()
     });
 
+    // `StdSignDoc` omits a zero timeout height
+    if body.timeout_height > 0 {
+        payload["timeout_height"] = body.timeout_height.to_string().into();
+    }
+
     println!("{}", serde_json::to_string_pretty(&payload)?);
 
     Ok(payload)
@@ -241,28 +460,107 @@ pub fn generate_legacy_amino_json(
 pub async fn get_account_number_and_sequence(
     rpc_endpoint: &str,
     address: &str,
-) -> Result<(u64, u64, Option<PubKey>)> {
+) -> Result<(u64, u64, Option<Any>)> {
     let info = crate::query::get_account_info(rpc_endpoint, address).await?;
 
-    let bacc = info
-        .account
-        .clone()
-        .context("no account")?
-        .unpack_as(BaseAccount::default())
-        .or_else(|_| {
-            info.account
-                .unwrap()
-                .unpack_as(ContinuousVestingAccount::default())
-                .map(|x| x.base_vesting_account.unwrap().base_account.unwrap())
-        })?;
-
-    Ok((
-        bacc.account_number,
-        bacc.sequence,
-        bacc.pub_key
-            .map(|x| x.unpack_as(PubKey::default()))
-            .transpose()?,
-    ))
+    let account = info.account.context("no account")?;
+
+    let bacc = if account.type_url == ETH_ACCOUNT_TYPE_URL {
+        EthAccount::decode(account.value.as_slice())?
+            .base_account
+            .context("no base account")?
+    } else if account.type_url == INJECTIVE_ETH_ACCOUNT_TYPE_URL {
+        InjectiveEthAccount::decode(account.value.as_slice())?
+            .base_account
+            .context("no base account")?
+    } else {
+        account
+            .clone()
+            .unpack_as(BaseAccount::default())
+            .or_else(|_| {
+                account
+                    .unpack_as(ContinuousVestingAccount::default())
+                    .map(|x| x.base_vesting_account.unwrap().base_account.unwrap())
+            })?
+    };
+
+    Ok((bacc.account_number, bacc.sequence, bacc.pub_key))
+}
+
+pub fn get_sequence(tx: &Tx) -> Result<u64> {
+    Ok(tx
+        .auth_info
+        .as_ref()
+        .context("no auth_info")?
+        .signer_infos
+        .first()
+        .context("no signer_info")?
+        .sequence)
+}
+
+pub fn set_sequence(mut tx: Tx, sequence: u64) -> Result<Tx> {
+    tx.auth_info
+        .as_mut()
+        .context("no auth_info")?
+        .signer_infos
+        .first_mut()
+        .context("no signer_info")?
+        .sequence = sequence;
+    Ok(tx)
+}
+
+/// Sets the mode of a single signer, which depends on the backend of the signer.
+pub fn set_sign_mode(mut tx: Tx, mode: SignMode) -> Result<Tx> {
+    let signer_info = tx
+        .auth_info
+        .as_mut()
+        .context("no auth_info")?
+        .signer_infos
+        .first_mut()
+        .context("no signer_info")?;
+    if let Some(ModeInfo {
+        sum: Some(Sum::Single(single)),
+    }) = signer_info.mode_info.as_mut()
+    {
+        single.mode = mode.into();
+    }
+    Ok(tx)
+}
+
+pub fn has_public_key(tx: &Tx) -> Result<bool> {
+    Ok(tx
+        .auth_info
+        .as_ref()
+        .context("no auth_info")?
+        .signer_infos
+        .first()
+        .context("no signer_info")?
+        .public_key
+        .is_some())
+}
+
+pub fn set_public_key(mut tx: Tx, public_key: Any) -> Result<Tx> {
+    tx.auth_info
+        .as_mut()
+        .context("no auth_info")?
+        .signer_infos
+        .first_mut()
+        .context("no signer_info")?
+        .public_key = Some(public_key);
+    Ok(tx)
+}
+
+pub fn fee_from_gas_price(gas_limit: u64, gas_price: f64) -> u128 {
+    (gas_limit as f64 * gas_price).ceil() as u128
+}
+
+pub fn update_tx_with_fee(mut tx: Tx, fee: (u128, &str)) -> Result<Tx> {
+    let auth_info = tx.auth_info.as_mut().context("no auth_info")?;
+    auth_info.fee.as_mut().context("no fee")?.amount = vec![Coin {
+        denom: fee.1.to_string(),
+        amount: fee.0.to_string(),
+    }];
+    Ok(tx)
 }
 
 pub fn update_tx_with_gas(mut tx: Tx, gas: u64) -> Result<Tx> {
@@ -1,3 +1,4 @@
+use anyhow::Context;
 use base64::prelude::{Engine as _, BASE64_STANDARD};
 use bech32::{Bech32, Hrp};
 use cosmos_sdk_proto::prost_wkt_types::MessageSerde;
@@ -24,6 +25,21 @@ where
     Ok(())
 }
 
+/// Writes a file readable only by the owner, for keys.
+pub fn write_private_file(path: impl AsRef<std::path::Path>, contents: &[u8]) -> Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut file = options.open(path.as_ref())?;
+    // `mode` only applies to new files
+    #[cfg(unix)]
+    file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+    std::io::Write::write_all(&mut file, contents)?;
+    Ok(())
+}
+
 pub fn read_bytes_from_file(file_name: &str) -> Result<Vec<u8>> {
     Ok(std::fs::read(file_name)?)
 }
@@ -67,6 +83,21 @@ pub fn parse_dec_amount(st: &str, precision: usize) -> Result<u128> {
         .unwrap_or_default())
 }
 
+/// Parses a decimal into its `LegacyDec` integer, with 18 decimals.
+pub fn parse_legacy_dec(st: &str) -> Result<u128> {
+    let (integer, fraction) = st.split_once('.').unwrap_or((st, ""));
+    (fraction.len() <= 18)
+        .then_some(())
+        .context(format!("{st} has more than 18 decimals"))?;
+    Ok(integer.parse::<u128>()? * 10u128.pow(18) + format!("{fraction:0<18}").parse::<u128>()?)
+}
+
+/// Formats a `LegacyDec` integer as a decimal, with 18 decimals.
+pub fn format_legacy_dec(dec: u128) -> String {
+    let one = 10u128.pow(18);
+    format!("{}.{:018}", dec / one, dec % one)
+}
+
 pub fn read_data_from_yaml<T>(path: &str) -> Result<T>
 where
     T: DeserializeOwned,
@@ -76,6 +107,17 @@ where
     Ok(serde_yaml::from_reader(reader)?)
 }
 
+/// Like `read_data_from_yaml`, but a missing file reads as the default.
+pub fn read_data_from_yaml_or_default<T>(path: &str) -> Result<T>
+where
+    T: DeserializeOwned + Default,
+{
+    match std::fs::File::open(path) {
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        file => Ok(serde_yaml::from_reader(std::io::BufReader::new(file?))?),
+    }
+}
+
 pub fn write_data_as_yaml<T>(path: &str, value: T) -> Result<()>
 where
     T: Serialize,
@@ -85,6 +127,24 @@ where
     Ok(serde_yaml::to_writer(writer, &value)?)
 }
 
+pub fn read_data_from_json<T>(path: &str) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    let file = std::fs::File::open(path)?;
+    let reader = std::io::BufReader::new(file);
+    Ok(serde_json::from_reader(reader)?)
+}
+
+pub fn write_data_as_json<T>(path: &str, value: T) -> Result<()>
+where
+    T: Serialize,
+{
+    let file = std::fs::File::create(path)?;
+    let writer = std::io::BufWriter::new(file);
+    Ok(serde_json::to_writer_pretty(writer, &value)?)
+}
+
 pub fn update_chain(chain_name: &str, path: &str, value: Value, file_path: &str) -> Result<()> {
     let mut chains: Value = crate::utils::read_data_from_yaml(file_path)?;
 
@@ -97,3 +157,42 @@ pub fn update_chain(chain_name: &str, path: &str, value: Value, file_path: &str)
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_legacy_dec() {
+        assert_eq!(parse_legacy_dec("1").unwrap(), 10u128.pow(18));
+        assert_eq!(parse_legacy_dec("0.25").unwrap(), 25 * 10u128.pow(16));
+        assert_eq!(
+            parse_legacy_dec("2.000000000000000001").unwrap(),
+            2 * 10u128.pow(18) + 1
+        );
+        assert!(parse_legacy_dec("0.0000000000000000001").is_err());
+        assert!(parse_legacy_dec("-1").is_err());
+        assert!(parse_legacy_dec("one").is_err());
+    }
+
+    #[test]
+    fn formats_legacy_dec() {
+        assert_eq!(format_legacy_dec(10u128.pow(18)), "1.000000000000000000");
+        assert_eq!(
+            format_legacy_dec(25 * 10u128.pow(16)),
+            "0.250000000000000000"
+        );
+        assert_eq!(format_legacy_dec(1), "0.000000000000000001");
+    }
+
+    #[test]
+    fn legacy_dec_round_trips() {
+        for dec in ["0.7", "12.345", "1000000"] {
+            let parsed = parse_legacy_dec(dec).unwrap();
+            assert_eq!(
+                parse_legacy_dec(&format_legacy_dec(parsed)).unwrap(),
+                parsed
+            );
+        }
+    }
+}
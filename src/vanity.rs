@@ -1,13 +1,14 @@
+use std::str::FromStr;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
 use std::time::SystemTime;
 
 use anyhow::Context;
 use bip32::secp256k1::elliptic_curve::rand_core::OsRng;
-use bip32::{Language, Mnemonic};
+use bip32::{DerivationPath, Language, Mnemonic};
 use rayon::prelude::*;
 
-use crate::keys::mnemonic_to_cosmos_addr;
+use crate::keys::{hd_path, mnemonic_to_cosmos_addr};
 use crate::Result;
 
 pub fn find_parallel(vanity_prefix: &str, coin_type: &u64) -> Result<(String, Mnemonic)> {
@@ -18,6 +19,8 @@ pub fn find_parallel(vanity_prefix: &str, coin_type: &u64) -> Result<(String, Mn
 
     let hrp = "1".to_owned();
 
+    let derivation_path = DerivationPath::from_str(&hd_path(*coin_type, 0, 0, 0))?;
+
     let ts = Arc::new(RwLock::new(SystemTime::now()));
 
     let ix = AtomicU64::new(0);
@@ -31,7 +34,7 @@ pub fn find_parallel(vanity_prefix: &str, coin_type: &u64) -> Result<(String, Mn
         .into_par_iter()
         .find_map_any(|_| {
             let mnemonic = Mnemonic::random(OsRng, Language::English);
-            let address = mnemonic_to_cosmos_addr(&mnemonic, &hrp, coin_type).ok()?;
+            let address = mnemonic_to_cosmos_addr(&mnemonic, &hrp, &derivation_path, "").ok()?;
 
             ix.fetch_add(1, Ordering::Relaxed);
 
@@ -61,5 +64,8 @@ pub fn find_parallel(vanity_prefix: &str, coin_type: &u64) -> Result<(String, Mn
         })
         .context("could not find any")?;
 
-    Ok((mnemonic_to_cosmos_addr(&mm, "cosmos", coin_type)?, mm))
+    Ok((
+        mnemonic_to_cosmos_addr(&mm, "cosmos", &derivation_path, "")?,
+        mm,
+    ))
 }